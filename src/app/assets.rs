@@ -0,0 +1,59 @@
+use macroquad::prelude::*;
+use std::path::PathBuf;
+
+/// Assets compiled into the binary, so it runs from any directory
+const EMBEDDED_ASSETS: [(&str, &[u8]); 6] = [
+    ("background.png", include_bytes!("../../assets/background.png")),
+    ("background_statistics.png", include_bytes!("../../assets/background_statistics.png")),
+    ("car1.png", include_bytes!("../../assets/car1.png")),
+    ("car2.png", include_bytes!("../../assets/car2.png")),
+    ("car3.png", include_bytes!("../../assets/car3.png")),
+    ("PlaypenSans.ttf", include_bytes!("../../assets/PlaypenSans.ttf")),
+];
+
+/// Finds the assets in an optional texture pack directory, then in the binary
+#[derive(Debug, Clone, Default)]
+pub struct AssetLoader {
+    pub dir: Option<PathBuf>,
+}
+
+impl AssetLoader {
+    fn bytes(&self, name: &str) -> Option<Vec<u8>> {
+        if let Some(dir) = &self.dir {
+            match std::fs::read(dir.join(name)) {
+                Ok(bytes) => return Some(bytes),
+                Err(error) => eprintln!("{}: {}, using the embedded asset", dir.join(name).display(), error),
+            }
+        }
+
+        EMBEDDED_ASSETS
+            .iter()
+            .find(|(asset_name, _)| *asset_name == name)
+            .map(|(_, bytes)| bytes.to_vec())
+    }
+
+    /// Returns `None` when the texture cannot be decoded, the scene being then drawn with plain shapes
+    pub fn texture(&self, name: &str) -> Option<Texture2D> {
+        let bytes = self.bytes(name)?;
+
+        match Image::from_file_with_format(&bytes, None) {
+            Ok(image) => Some(Texture2D::from_image(&image)),
+            Err(error) => {
+                eprintln!("{}: {}", name, error);
+                self.embedded_texture(name)
+            }
+        }
+    }
+
+    fn embedded_texture(&self, name: &str) -> Option<Texture2D> {
+        self.dir.as_ref()?;
+
+        AssetLoader { dir: None }.texture(name)
+    }
+
+    pub fn font(&self, name: &str) -> Option<Font> {
+        load_ttf_font_from_bytes(&self.bytes(name)?)
+            .map_err(|error| eprintln!("{}: {}", name, error))
+            .ok()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::constant::WORLD_SIZE;
+use macroquad::prelude::*;
+
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 8.0;
+
+/// Maps the world, in metres, to the window whatever its size: at zoom 1 the
+/// whole world fits in the smallest window dimension
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub target: Vec2,
+    pub zoom: f32,
+
+    /// Size of the shown world, a single intersection or a whole network
+    pub world: Vec2,
+
+    /// Mouse position of the previous frame while panning
+    drag_from: Option<Vec2>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::fitting(Vec2::splat(WORLD_SIZE))
+    }
+}
+
+impl Camera {
+    pub fn fitting(world: Vec2) -> Camera {
+        Camera {
+            target: world / 2.0,
+            zoom: 1.0,
+            world,
+            drag_from: None,
+        }
+    }
+
+    pub fn camera_2d(&self) -> Camera2D {
+        self.camera_2d_at(Vec2::ZERO)
+    }
+
+    /// Camera drawing in the coordinates of an intersection whose top-left corner is at `origin`
+    pub fn camera_2d_at(&self, origin: Vec2) -> Camera2D {
+        let aspect = screen_width() / screen_height().max(1.0);
+        let size = self.world.max_element() / self.zoom;
+        let (width, height) = if aspect >= 1.0 {
+            (size * aspect, size)
+        } else {
+            (size, size / aspect)
+        };
+
+        Camera2D::from_display_rect(Rect::new(
+            self.target.x - origin.x - width / 2.0,
+            self.target.y - origin.y - height / 2.0,
+            width,
+            height,
+        ))
+    }
+
+    pub fn screen_to_world(&self, position: Vec2) -> Vec2 {
+        self.camera_2d().screen_to_world(position)
+    }
+
+    /// Zooms with the mouse wheel around the cursor and pans while the right button is held
+    pub fn handle_input(&mut self) {
+        let mouse = Vec2::from(mouse_position());
+
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0.0 {
+            let anchor = self.screen_to_world(mouse);
+            self.zoom = (self.zoom * ZOOM_STEP.powf(wheel.signum())).clamp(MIN_ZOOM, MAX_ZOOM);
+            self.target += anchor - self.screen_to_world(mouse);
+        }
+
+        if is_mouse_button_down(MouseButton::Right) {
+            if let Some(drag_from) = self.drag_from {
+                self.target += self.screen_to_world(drag_from) - self.screen_to_world(mouse);
+            }
+            self.drag_from = Some(mouse);
+        } else {
+            self.drag_from = None;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Camera::fitting(self.world);
+    }
+}
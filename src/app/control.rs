@@ -1,7 +1,23 @@
-use crate::traffic::{Direction, TrafficState};
+use crate::app::{Rewind, View};
+use crate::constant::{CAR_LENGTH, ROAD_WIDTH};
+use crate::draw::{save_svg, timeline_area, timeline_time};
+use crate::traffic::{Direction, Network, Path, Snapshot, TrafficState};
 use macroquad::prelude::*;
+use std::rc::Rc;
 
-pub fn handle_input(traffic_state: &mut TrafficState) {
+/// File written when exporting the heatmap from the window
+const HEATMAP_FILE: &str = "heatmap.png";
+
+/// File written when exporting an SVG picture from the window
+const SVG_FILE: &str = "snapshot.svg";
+
+/// File written and read by the save and load keys
+const SAVE_FILE: &str = "simulation.json";
+
+/// Half width of a lane, for the click detection on the approach roads
+const LANE_HALF_WIDTH: f32 = ROAD_WIDTH / 12.0;
+
+pub fn handle_input(traffic_state: &mut TrafficState, view: &mut View, rewind: &mut Rewind) {
     if traffic_state.statistics.is_open {
         if is_key_pressed(KeyCode::Escape) {
             std::process::exit(0);
@@ -9,6 +25,12 @@ pub fn handle_input(traffic_state: &mut TrafficState) {
         if is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::P) {
             traffic_state.toggle_pause();
         }
+        if is_key_pressed(KeyCode::Right) {
+            view.chart_page = view.chart_page.next();
+        }
+        if is_key_pressed(KeyCode::Left) {
+            view.chart_page = view.chart_page.previous();
+        }
         return;
     }
 
@@ -16,6 +38,8 @@ pub fn handle_input(traffic_state: &mut TrafficState) {
         traffic_state.toggle_pause();
     }
 
+    handle_rewind(traffic_state, rewind);
+
     if is_key_pressed(KeyCode::Up) {
         traffic_state.gen_car(Direction::South);
     }
@@ -32,7 +56,173 @@ pub fn handle_input(traffic_state: &mut TrafficState) {
         traffic_state.gen_car(Direction::East);
     }
 
+    view.camera.handle_input();
+    if is_key_pressed(KeyCode::C) {
+        view.camera.reset();
+    }
+
+    if is_key_pressed(KeyCode::H) {
+        view.toggle_hud();
+    }
+
+    if is_key_pressed(KeyCode::M) {
+        view.toggle_heatmap();
+    }
+
+    if is_key_pressed(KeyCode::X) {
+        traffic_state.statistics.heatmap.reset();
+    }
+
+    if is_key_pressed(KeyCode::E) {
+        let heatmap = &traffic_state.statistics.heatmap;
+        if let Err(error) = heatmap.export_png(std::path::Path::new(HEATMAP_FILE), traffic_state.params.max_speed) {
+            eprintln!("{}", error);
+        }
+    }
+
+    if is_key_pressed(KeyCode::F12) {
+        if let Err(error) = save_svg(traffic_state, std::path::Path::new(SVG_FILE)) {
+            eprintln!("{}", error);
+        }
+    }
+
+    if is_key_pressed(KeyCode::F5) {
+        if let Err(error) = traffic_state.save(std::path::Path::new(SAVE_FILE)) {
+            eprintln!("{}", error);
+        }
+    }
+
+    if is_key_pressed(KeyCode::F9) {
+        // the snapshot is replayed on the lanes of the intersection being shown, which must be its own
+        match Snapshot::load(std::path::Path::new(SAVE_FILE)).and_then(|snapshot| TrafficState::restore(&snapshot, traffic_state.geometry.clone())) {
+            Ok(restored) => {
+                *traffic_state = restored;
+                view.selected_car = None;
+                rewind.clear();
+            }
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+
+    handle_debug_keys(view);
+
     if is_key_down(KeyCode::R) {
         traffic_state.gen_car_random();
     }
+
+    let on_timeline = rewind.is_scrubbing() && timeline_area().contains(mouse_position().into());
+    if is_mouse_button_pressed(MouseButton::Left) && !on_timeline {
+        let position = view.camera.screen_to_world(mouse_position().into());
+        handle_click(traffic_state, view, position);
+    }
+}
+
+/// Keys of the network window: pause, camera, overlays
+pub fn handle_network_input(network: &mut Network, view: &mut View) {
+    if is_key_pressed(KeyCode::Escape) {
+        std::process::exit(0);
+    }
+
+    if is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::P) {
+        network.toggle_pause();
+    }
+
+    view.camera.handle_input();
+    if is_key_pressed(KeyCode::C) {
+        view.camera.reset();
+    }
+
+    if is_key_pressed(KeyCode::H) {
+        view.toggle_hud();
+    }
+
+    if is_key_pressed(KeyCode::M) {
+        view.toggle_heatmap();
+    }
+
+    handle_debug_keys(view);
+}
+
+fn handle_debug_keys(view: &mut View) {
+    let debug = &mut view.debug;
+    for (key, layer) in [
+        (KeyCode::Key1, &mut debug.paths),
+        (KeyCode::Key2, &mut debug.conflicts),
+        (KeyCode::Key3, &mut debug.envelopes),
+        (KeyCode::Key4, &mut debug.leaders),
+        (KeyCode::Key5, &mut debug.intersection),
+        (KeyCode::Key6, &mut debug.status),
+    ] {
+        if is_key_pressed(key) {
+            *layer = !*layer;
+        }
+    }
+}
+
+/// Scrubbing keys, and dragging on the timeline while scrubbing
+fn handle_rewind(traffic_state: &mut TrafficState, rewind: &mut Rewind) {
+    if is_key_pressed(KeyCode::J) {
+        rewind.auto_jump = !rewind.auto_jump;
+    }
+
+    if is_key_pressed(KeyCode::Comma) {
+        rewind.step_back(traffic_state);
+    }
+
+    if is_key_pressed(KeyCode::Period) {
+        rewind.step_forward(traffic_state);
+    }
+
+    if is_key_pressed(KeyCode::Enter) {
+        rewind.resume(traffic_state);
+    }
+
+    if is_key_pressed(KeyCode::Backspace) {
+        rewind.resume_live(traffic_state);
+    }
+
+    let (x, y) = mouse_position();
+    if rewind.is_scrubbing() && is_mouse_button_down(MouseButton::Left) && timeline_area().contains(vec2(x, y)) {
+        if let Some(time) = timeline_time(rewind, x) {
+            rewind.seek(time, traffic_state);
+        }
+    }
+}
+
+/// Selects the clicked car, or spawns a car on the clicked approach lane
+fn handle_click(traffic_state: &mut TrafficState, view: &mut View, position: Vec2) {
+    let clicked_car = traffic_state
+        .cars()
+        .find(|car| car.center().distance(position) < CAR_LENGTH / 2.0)
+        .map(|car| car.id);
+
+    if clicked_car.is_some() {
+        view.selected_car = clicked_car;
+        return;
+    }
+
+    match clicked_lane(traffic_state, position) {
+        Some(path) => traffic_state.gen_car_on_path(&path),
+        None => view.selected_car = None,
+    }
+}
+
+/// Returns the path whose approach segment, before the intersection, contains `position`
+fn clicked_lane(traffic_state: &TrafficState, position: Vec2) -> Option<Rc<Path>> {
+    traffic_state
+        .lines
+        .iter()
+        .flat_map(|line| line.paths.iter())
+        .find(|path| match (path.point(0), path.point(1)) {
+            (Some(start), Some(end)) => segment_distance(position, start, end) < LANE_HALF_WIDTH,
+            _ => false,
+        })
+        .cloned()
+}
+
+fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = ((point - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0);
+
+    point.distance(start + segment * t)
 }
@@ -0,0 +1,278 @@
+use crate::app::{Run, Scenario};
+use crate::constant::{TICK, WORLD_SIZE};
+use crate::traffic::{CarStatus, Direction, ExternalControl, Going, TrafficState};
+use macroquad::math::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Reinforcement learning environment over a scenario, read from a TOML file:
+///
+/// ```toml
+/// duration = 600          # simulated seconds of an episode
+/// decision_steps = 6      # simulation steps played per action
+/// observation_radius = 35 # metres around the centre of the intersection
+/// end_on_collision = true
+///
+/// [reward]
+/// throughput = 1.0 # per car leaving the map
+/// delay = 0.1      # per second lost against the maximum speed, summed over the cars
+/// close_call = 1.0
+/// collision = 100.0
+///
+/// [scenario.demand] # same format as `run --scenario`
+/// north = 300
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    pub duration: f64,
+    pub decision_steps: usize,
+    pub observation_radius: f32,
+    pub end_on_collision: bool,
+    pub reward: RewardWeights,
+    pub scenario: Scenario,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RewardWeights {
+    pub throughput: f64,
+    pub delay: f64,
+    pub close_call: f64,
+    pub collision: f64,
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        EnvironmentConfig {
+            duration: 600.0,
+            decision_steps: 6,
+            observation_radius: WORLD_SIZE / 2.0,
+            end_on_collision: true,
+            reward: RewardWeights::default(),
+            scenario: Scenario::default(),
+        }
+    }
+}
+
+impl Default for RewardWeights {
+    fn default() -> Self {
+        RewardWeights {
+            throughput: 1.0,
+            delay: 0.1,
+            close_call: 1.0,
+            collision: 100.0,
+        }
+    }
+}
+
+/// Decision of the agent, applied until the next step. An empty action keeps the
+/// built-in rule, `admit` lists the only cars allowed to enter the intersection
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Action {
+    pub admit: Option<Vec<usize>>,
+
+    /// Speed limits in m/s, by car id
+    pub target_speeds: HashMap<usize, f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Observation {
+    pub time: f64,
+    pub cars: Vec<ObservedCar>,
+}
+
+/// Car within `observation_radius` of the centre of the intersection
+#[derive(Debug, Clone, Serialize)]
+pub struct ObservedCar {
+    pub id: usize,
+    pub coming_from: Direction,
+    pub going_to: Going,
+
+    /// Still waiting to enter the intersection
+    pub approaching: bool,
+
+    /// Distance from the middle of the car to the centre of the intersection, in metres
+    pub distance: f32,
+    pub velocity: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub observation: Observation,
+    pub reward: f64,
+
+    /// The episode ended on a collision
+    pub terminated: bool,
+
+    /// The episode reached its duration
+    pub truncated: bool,
+    pub info: StepInfo,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StepInfo {
+    pub completed: usize,
+    pub close_calls: usize,
+    pub collisions: usize,
+}
+
+pub struct Environment {
+    pub config: EnvironmentConfig,
+    run: Run,
+    traffic_state: TrafficState,
+}
+
+impl EnvironmentConfig {
+    pub fn load(path: &Path) -> Result<EnvironmentConfig, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+
+        toml::from_str(&content).map_err(|error| format!("invalid environment {}: {}", path.display(), error))
+    }
+}
+
+impl Environment {
+    pub fn new(config: EnvironmentConfig, seed: u64) -> Environment {
+        let run = Run {
+            scenario: config.scenario.clone(),
+            duration: config.duration,
+            seed,
+            initial: None,
+        };
+        let traffic_state = run.traffic_state();
+
+        Environment {
+            config,
+            run,
+            traffic_state,
+        }
+    }
+
+    /// Starts a new episode, with the next seed when none is given
+    pub fn reset(&mut self, seed: Option<u64>) -> Observation {
+        self.run.seed = seed.unwrap_or(self.run.seed.wrapping_add(1));
+        self.traffic_state = self.run.traffic_state();
+
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: &Action) -> StepResult {
+        self.traffic_state.external_control = ExternalControl {
+            admitted: action.admit.as_ref().map(|admit| admit.iter().copied().collect()),
+            target_speeds: action.target_speeds.clone(),
+        };
+
+        let weights = self.config.reward;
+        let statistics = &self.traffic_state.statistics;
+        let (completed, close_calls, collisions) = (
+            statistics.travel_times.len(),
+            statistics.close_calls.len(),
+            statistics.collisions.len(),
+        );
+
+        let mut delay = 0.0;
+        for _ in 0..self.config.decision_steps.max(1) {
+            if self.is_terminated() || self.run.is_finished(&self.traffic_state) {
+                break;
+            }
+
+            self.run.step(&mut self.traffic_state);
+
+            let max_speed = self.traffic_state.params.max_speed;
+            delay += self
+                .traffic_state
+                .cars()
+                .map(|car| (1.0 - car.velocity / max_speed).max(0.0) as f64 * TICK)
+                .sum::<f64>();
+        }
+
+        let statistics = &self.traffic_state.statistics;
+        let info = StepInfo {
+            completed: statistics.travel_times.len() - completed,
+            close_calls: statistics.close_calls.len() - close_calls,
+            collisions: statistics.collisions.len() - collisions,
+        };
+
+        StepResult {
+            observation: self.observe(),
+            reward: weights.throughput * info.completed as f64
+                - weights.delay * delay
+                - weights.close_call * info.close_calls as f64
+                - weights.collision * info.collisions as f64,
+            terminated: self.is_terminated(),
+            truncated: self.run.is_finished(&self.traffic_state),
+            info,
+        }
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.config.end_on_collision && !self.traffic_state.statistics.collisions.is_empty()
+    }
+
+    pub fn observe(&self) -> Observation {
+        let centre = Vec2::splat(WORLD_SIZE / 2.0);
+
+        let mut cars = self
+            .traffic_state
+            .cars()
+            .map(|car| ObservedCar {
+                id: car.id,
+                coming_from: car.path.coming_from,
+                going_to: car.path.going_to,
+                approaching: car.get_status() == CarStatus::BeforeTurn,
+                distance: car.center().distance(centre),
+                velocity: car.velocity,
+            })
+            .filter(|car| car.distance <= self.config.observation_radius)
+            .collect::<Vec<_>>();
+        cars.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        Observation {
+            time: self.traffic_state.time,
+            cars,
+        }
+    }
+}
+
+/// Request read by `serve_stdio`, one JSON object per line:
+///
+/// ```json
+/// {"reset": {"seed": 3}}
+/// {"step": {"admit": [12, 15]}}
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Request {
+    Reset {
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    Step(Action),
+}
+
+/// Drives the environment from JSON lines, so that agents written in other
+/// languages can train on it; every request gets one JSON line in answer
+pub fn serve_stdio(environment: &mut Environment, input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+    for line in input.lines() {
+        let line = line.map_err(|error| error.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let answer = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Reset { seed }) => serde_json::json!({ "observation": environment.reset(seed) }),
+            Ok(Request::Step(action)) => serde_json::json!(environment.step(&action)),
+            Err(error) => serde_json::json!({ "error": error.to_string() }),
+        };
+
+        writeln!(output, "{}", answer)
+            .and_then(|_| output.flush())
+            .map_err(|error| error.to_string())?;
+    }
+
+    Ok(())
+}
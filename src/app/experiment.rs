@@ -0,0 +1,329 @@
+use crate::app::{Report, Run, Scenario};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Grid of parameters played over several seeds, read from a TOML file:
+///
+/// ```toml
+/// seeds = 10
+/// duration = 1800
+///
+/// [scenario.demand] # base scenario, same format as `run --scenario`
+/// north = 300
+///
+/// [grid] # every combination of these values is played
+/// safe_distance = [8, 10, 12]
+/// demand = [200, 400]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Experiment {
+    #[serde(default = "default_seeds")]
+    pub seeds: u64,
+    #[serde(default = "default_duration")]
+    pub duration: f64,
+    #[serde(default)]
+    pub scenario: Scenario,
+    #[serde(default)]
+    pub grid: BTreeMap<String, Vec<f64>>,
+}
+
+fn default_seeds() -> u64 {
+    10
+}
+
+fn default_duration() -> f64 {
+    3600.0
+}
+
+/// Values given to the grid parameters, in the order of `Experiment::grid`
+pub type Combination = Vec<(String, f64)>;
+
+/// Mean of a metric over the seeds of a combination, with its 95% confidence interval
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    pub mean: f64,
+    pub half_width: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExperimentRow {
+    pub combination: Combination,
+    pub throughput_per_hour: Estimate,
+    pub mean_time: Estimate,
+    pub max_time: Estimate,
+    pub close_calls: Estimate,
+    pub collisions: Estimate,
+}
+
+impl Experiment {
+    pub fn load(path: &Path) -> Result<Experiment, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+
+        toml::from_str(&content).map_err(|error| format!("invalid experiment {}: {}", path.display(), error))
+    }
+
+    /// Cartesian product of the grid values
+    pub fn combinations(&self) -> Vec<Combination> {
+        self.grid.iter().fold(vec![vec![]], |combinations, (name, values)| {
+            combinations
+                .iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |&value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.clone(), value));
+                        combination
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Plays every combination with every seed on `threads` worker threads
+    pub fn run(&self, first_seed: u64, threads: usize) -> Result<Vec<ExperimentRow>, String> {
+        let combinations = self.combinations();
+
+        let mut runs = Vec::new();
+        for (index, combination) in combinations.iter().enumerate() {
+            let mut scenario = self.scenario.clone();
+            for (name, value) in combination {
+                scenario.set(name, *value)?;
+            }
+
+            for seed in first_seed..first_seed + self.seeds {
+                runs.push((
+                    index,
+                    Run {
+                        scenario: scenario.clone(),
+                        duration: self.duration,
+                        seed,
+                        initial: None,
+                    },
+                ));
+            }
+        }
+
+        let next_run = AtomicUsize::new(0);
+        let reports: Mutex<Vec<Vec<Report>>> = Mutex::new(vec![vec![]; combinations.len()]);
+
+        std::thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| {
+                    while let Some((index, run)) = runs.get(next_run.fetch_add(1, Ordering::SeqCst)) {
+                        let report = run.run_headless();
+                        reports.lock().unwrap()[*index].push(report);
+                    }
+                });
+            }
+        });
+
+        Ok(combinations
+            .into_iter()
+            .zip(reports.into_inner().unwrap())
+            .map(|(combination, reports)| ExperimentRow::new(combination, &reports))
+            .collect())
+    }
+}
+
+impl Estimate {
+    pub fn new(samples: &[f64]) -> Estimate {
+        let count = samples.len();
+        if count == 0 {
+            return Estimate { mean: 0.0, half_width: 0.0 };
+        }
+
+        let mean = samples.iter().sum::<f64>() / count as f64;
+        if count == 1 {
+            return Estimate { mean, half_width: 0.0 };
+        }
+
+        let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+
+        Estimate {
+            mean,
+            half_width: student_t95(count - 1) * (variance / count as f64).sqrt(),
+        }
+    }
+}
+
+/// Two-sided 95% quantile of the Student t distribution
+fn student_t95(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179,
+        2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064,
+        2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+
+    TABLE.get(degrees_of_freedom - 1).copied().unwrap_or(1.96)
+}
+
+impl ExperimentRow {
+    fn new(combination: Combination, reports: &[Report]) -> ExperimentRow {
+        let estimate = |metric: fn(&Report) -> f64| {
+            Estimate::new(&reports.iter().map(metric).collect::<Vec<_>>())
+        };
+
+        ExperimentRow {
+            combination,
+            throughput_per_hour: estimate(|report| report.throughput_per_hour),
+            mean_time: estimate(|report| report.mean_time),
+            max_time: estimate(|report| report.max_time),
+            close_calls: estimate(|report| report.close_calls as f64),
+            collisions: estimate(|report| report.collisions as f64),
+        }
+    }
+
+    fn estimates(&self) -> [Estimate; 5] {
+        [
+            self.throughput_per_hour,
+            self.mean_time,
+            self.max_time,
+            self.close_calls,
+            self.collisions,
+        ]
+    }
+}
+
+const METRICS: [&str; 5] = ["throughput/h", "mean time", "max time", "close calls", "collisions"];
+
+/// Aligned text table, one line per combination, metrics written as `mean ± ci`
+pub fn format_table(rows: &[ExperimentRow]) -> String {
+    let Some(first) = rows.first() else {
+        return String::new();
+    };
+
+    let header = first
+        .combination
+        .iter()
+        .map(|(name, _)| name.clone())
+        .chain(METRICS.iter().map(|metric| metric.to_string()))
+        .collect::<Vec<_>>();
+
+    let cells = rows
+        .iter()
+        .map(|row| {
+            row.combination
+                .iter()
+                .map(|(_, value)| format!("{}", value))
+                .chain(
+                    row.estimates()
+                        .iter()
+                        .map(|estimate| format!("{:.2} ± {:.2}", estimate.mean, estimate.half_width)),
+                )
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let widths = (0..header.len())
+        .map(|column| {
+            cells
+                .iter()
+                .map(|line| line[column].chars().count())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = String::new();
+    for line in [header].iter().chain(cells.iter()) {
+        for (cell, width) in line.iter().zip(widths.iter()) {
+            let _ = write!(table, "{:>width$}  ", cell, width = width);
+        }
+        table.truncate(table.trim_end().len());
+        table.push('\n');
+    }
+
+    table
+}
+
+/// Same table as CSV, with separate columns for the means and the interval half widths
+pub fn format_csv(rows: &[ExperimentRow]) -> String {
+    let Some(first) = rows.first() else {
+        return String::new();
+    };
+
+    let mut csv = first
+        .combination
+        .iter()
+        .map(|(name, _)| name.clone())
+        .chain(
+            METRICS
+                .iter()
+                .flat_map(|metric| [metric.to_string(), format!("{} ci", metric)]),
+        )
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+
+    for row in rows {
+        let line = row
+            .combination
+            .iter()
+            .map(|(_, value)| value.to_string())
+            .chain(
+                row.estimates()
+                    .iter()
+                    .flat_map(|estimate| [estimate.mean.to_string(), estimate.half_width.to_string()]),
+            )
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push_str(&line);
+        csv.push('\n');
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn a_single_seed_has_no_interval() {
+        let estimate = Estimate::new(&[42.0]);
+        assert_close(estimate.mean, 42.0);
+        assert_close(estimate.half_width, 0.0);
+
+        let estimate = Estimate::new(&[]);
+        assert_close(estimate.mean, 0.0);
+        assert_close(estimate.half_width, 0.0);
+    }
+
+    #[test]
+    fn small_samples_widen_the_interval_by_the_student_quantile() {
+        // Standard deviation √2 over two samples: a standard error of 1
+        let estimate = Estimate::new(&[1.0, 3.0]);
+        assert_close(estimate.mean, 2.0);
+        assert_close(estimate.half_width, 12.706);
+
+        // Standard deviation 1 over four samples: a standard error of 1/2
+        let samples = [1.0, 1.0, 3.0, 3.0].map(|sample: f64| sample * 3.0_f64.sqrt() / 2.0);
+        let estimate = Estimate::new(&samples);
+        assert_close(estimate.half_width, 3.182 / 2.0);
+    }
+
+    #[test]
+    fn student_quantiles_match_the_table_values() {
+        for (degrees_of_freedom, quantile) in [(1, 12.706), (2, 4.303), (5, 2.571), (10, 2.228), (30, 2.042)] {
+            assert_close(student_t95(degrees_of_freedom), quantile);
+        }
+        assert_close(student_t95(31), 1.96);
+        assert_close(student_t95(1000), 1.96);
+    }
+
+    #[test]
+    fn constant_samples_have_no_interval() {
+        let estimate = Estimate::new(&[7.0; 10]);
+        assert_close(estimate.mean, 7.0);
+        assert_close(estimate.half_width, 0.0);
+    }
+}
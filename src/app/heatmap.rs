@@ -0,0 +1,103 @@
+use crate::constant::{HEATMAP_CELL_SIZE, TICK, WORLD_SIZE};
+use crate::traffic::Car;
+use macroquad::prelude::{Color, Image, Vec2};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Time spent and mean speed of the cars over a grid covering the world
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub columns: usize,
+    pub rows: usize,
+
+    /// Seconds spent by cars in each cell, row by row
+    pub occupancy: Vec<f32>,
+    /// Sum of `velocity * TICK` in each cell, divided by the occupancy for the mean speed
+    speed_sum: Vec<f32>,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        let size = (WORLD_SIZE / HEATMAP_CELL_SIZE).ceil() as usize;
+
+        Heatmap {
+            columns: size,
+            rows: size,
+            occupancy: vec![0.0; size * size],
+            speed_sum: vec![0.0; size * size],
+        }
+    }
+}
+
+impl Heatmap {
+    fn cell(&self, position: Vec2) -> Option<usize> {
+        let column = (position.x / HEATMAP_CELL_SIZE).floor();
+        let row = (position.y / HEATMAP_CELL_SIZE).floor();
+
+        if column < 0.0 || row < 0.0 || column as usize >= self.columns || row as usize >= self.rows {
+            return None;
+        }
+
+        Some(row as usize * self.columns + column as usize)
+    }
+
+    pub fn update(&mut self, cars: &[&Car]) {
+        for car in cars {
+            if let Some(cell) = self.cell(car.center()) {
+                self.occupancy[cell] += TICK as f32;
+                self.speed_sum[cell] += car.velocity * TICK as f32;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Heatmap::default();
+    }
+
+    pub fn mean_speed(&self, cell: usize) -> Option<f32> {
+        (self.occupancy[cell] > 0.0).then(|| self.speed_sum[cell] / self.occupancy[cell])
+    }
+
+    /// Colour of every cell: opacity grows with the occupancy, from green when
+    /// cars drive at `max_speed` to red when they are stopped
+    pub fn colors(&self, max_speed: f32) -> Vec<Color> {
+        let max_occupancy = self.occupancy.iter().copied().fold(0.0, f32::max);
+
+        (0..self.occupancy.len())
+            .map(|cell| {
+                let Some(mean_speed) = self.mean_speed(cell) else {
+                    return Color::new(0.0, 0.0, 0.0, 0.0);
+                };
+
+                let slowness = 1.0 - (mean_speed / max_speed.max(f32::EPSILON)).clamp(0.0, 1.0);
+                let intensity = (self.occupancy[cell] / max_occupancy).sqrt();
+
+                Color::new(slowness, 1.0 - slowness, 0.0, 0.2 + 0.6 * intensity)
+            })
+            .collect()
+    }
+
+    /// Heatmap as an image of one pixel per cell
+    pub fn to_image(&self, max_speed: f32) -> Image {
+        let mut image = Image::gen_image_color(self.columns as u16, self.rows as u16, Color::new(0.0, 0.0, 0.0, 0.0));
+
+        for (cell, color) in self.colors(max_speed).into_iter().enumerate() {
+            image.set_pixel((cell % self.columns) as u32, (cell / self.columns) as u32, color);
+        }
+
+        image
+    }
+
+    pub fn export_png(&self, path: &Path, max_speed: f32) -> Result<(), String> {
+        let image = self.to_image(max_speed);
+
+        image::save_buffer(
+            path,
+            &image.bytes,
+            image.width as u32,
+            image.height as u32,
+            image::ColorType::Rgba8,
+        )
+        .map_err(|error| format!("cannot write {}: {}", path.display(), error))
+    }
+}
@@ -1,75 +1,140 @@
 use crate::app::control::*;
+use crate::app::{AssetLoader, Rewind, Run, RunOutputs, View};
 use crate::draw::*;
 use crate::traffic::TrafficState;
 use macroquad::prelude::*;
-use std::path::PathBuf;
 
 pub struct App {
     pub traffic_state: TrafficState,
-    pub background_texture: Texture2D,
-    pub background_statistics_texture: Texture2D,
-    pub car_textures: (Texture2D, Texture2D, Texture2D),
+    pub view: View,
+    pub rewind: Rewind,
+    pub background_texture: Option<Texture2D>,
+    pub background_statistics_texture: Option<Texture2D>,
+    pub car_textures: (Option<Texture2D>, Option<Texture2D>, Option<Texture2D>),
     pub font: Option<Font>,
+
+    /// Scenario being played, if the app was started with `run`
+    pub run: Option<Run>,
+    pub outputs: RunOutputs,
 }
 
 impl App {
-    pub async fn new() -> Self {
-        let traffic_state = TrafficState::new();
-        let background_texture = load_texture_from_assets("background.png").await.unwrap();
-        let background_statistics_texture = load_texture_from_assets("background_statistics.png")
-            .await
-            .unwrap();
+    pub async fn new(
+        traffic_state: TrafficState,
+        run: Option<Run>,
+        outputs: RunOutputs,
+        assets: AssetLoader,
+    ) -> Self {
+        let background_texture = assets.texture("background.png");
+        let background_statistics_texture = assets.texture("background_statistics.png");
         let car_textures = (
-            load_texture_from_assets("car1.png").await.unwrap(),
-            load_texture_from_assets("car2.png").await.unwrap(),
-            load_texture_from_assets("car3.png").await.unwrap(),
+            assets.texture("car1.png"),
+            assets.texture("car2.png"),
+            assets.texture("car3.png"),
         );
-        let font = load_ttf_font("./assets/PlaypenSans.ttf").await.ok();
-        
+        let font = assets.font("PlaypenSans.ttf");
+
+        if run.is_some() {
+            if let Err(error) = outputs.write_snapshots(&traffic_state) {
+                eprintln!("{}", error);
+            }
+        }
+
         Self {
             traffic_state,
+            view: View::default(),
+            rewind: Rewind::default(),
             background_texture,
             background_statistics_texture,
             car_textures,
             font,
+            run,
+            outputs,
         }
     }
 
     pub async fn run(&mut self) {
         loop {
-            handle_input(&mut self.traffic_state);
+            handle_input(&mut self.traffic_state, &mut self.view, &mut self.rewind);
+            #[cfg(feature = "server")]
+            crate::app::apply_commands(&mut self.traffic_state);
+            #[cfg(feature = "server")]
+            crate::app::stream_frame(&self.traffic_state);
 
             if self.traffic_state.statistics.is_open {
+                set_default_camera();
                 draw_statistics(
                     &self.traffic_state.statistics,
-                    &self.background_statistics_texture,
+                    &self.traffic_state.approaches(),
+                    self.background_statistics_texture.as_ref(),
                     self.font.as_ref(),
+                    self.view.chart_page,
                 );
                 next_frame().await;
                 continue;
             }
 
-            self.traffic_state.update();
+            match &self.run {
+                _ if self.rewind.is_scrubbing() => {}
+                Some(run) if run.is_finished(&self.traffic_state) => self.finish_run(),
+                Some(run) => {
+                    run.step(&mut self.traffic_state);
+                    if let Err(error) = self.outputs.write_snapshots(&self.traffic_state) {
+                        eprintln!("{}", error);
+                    }
+                }
+                None => self.traffic_state.update(),
+            }
+            self.rewind.record(&mut self.traffic_state);
+            #[cfg(feature = "server")]
+            crate::app::stream_frame(&self.traffic_state);
 
-            draw_background(&self.background_texture);
+            set_camera(&self.view.camera.camera_2d());
+            draw_background(self.background_texture.as_ref(), &self.traffic_state.geometry);
+
+            if self.view.show_heatmap {
+                draw_heatmap(&self.traffic_state.statistics.heatmap, self.traffic_state.params.max_speed);
+            }
 
             for line in self.traffic_state.lines.iter() {
                 for path in line.paths.iter() {
-                    draw_path(path);
-
                     line.path_cars(path).iter().for_each(|car| {
                         draw_car(car, &self.car_textures);
                     });
                 }
             }
+
+            draw_debug(&self.traffic_state, &self.view.debug);
+
+            if let Some(selected_car) = self.view.selected_car {
+                draw_selected_path(&self.traffic_state, selected_car);
+            }
+
+            set_default_camera();
+
+            if let Some(selected_car) = self.view.selected_car {
+                draw_selection_panel(&self.traffic_state, selected_car, self.font.as_ref());
+            }
+
+            if self.view.show_hud {
+                draw_hud(&self.traffic_state, self.font.as_ref());
+            }
+
+            if self.rewind.is_scrubbing() {
+                draw_timeline(&self.rewind, &self.traffic_state, self.font.as_ref());
+            }
+
             next_frame().await;
         }
     }
-}
 
-async fn load_texture_from_assets(asset_path: &str) -> Result<Texture2D, macroquad::Error> {
-    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    path.push("assets");
-    path.push(asset_path);
-    load_texture(path.to_str().unwrap()).await
+    /// Writes the outputs of the scenario and shows the statistics screen
+    fn finish_run(&mut self) {
+        if let Some(run) = self.run.take() {
+            if let Err(error) = self.outputs.write(&run, &self.traffic_state) {
+                eprintln!("{}", error);
+            }
+            self.traffic_state.toggle_pause();
+        }
+    }
 }
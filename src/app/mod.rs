@@ -1,6 +1,34 @@
+mod assets;
+mod camera;
+mod environment;
 mod control;
+mod experiment;
+mod heatmap;
 mod init;
+mod network_window;
+mod report;
+mod rewind;
+mod run;
+mod scenario;
+#[cfg(feature = "server")]
+mod server;
 mod statistics;
+mod terminal;
+mod view;
 
+pub use assets::*;
+pub use camera::*;
+pub use environment::*;
+pub use experiment::*;
+pub use heatmap::*;
 pub use init::*;
+pub use network_window::*;
+pub use report::*;
+pub use rewind::*;
+pub use run::*;
+pub use scenario::*;
+#[cfg(feature = "server")]
+pub use server::*;
 pub use statistics::*;
+pub use terminal::*;
+pub use view::*;
@@ -0,0 +1,84 @@
+use crate::app::control::handle_network_input;
+use crate::app::{AssetLoader, Camera, Scenario, View};
+use crate::draw::*;
+use crate::traffic::{Direction, Network};
+use macroquad::prelude::*;
+
+/// Window showing a whole road network, every intersection drawn at its place on the grid
+pub struct NetworkApp {
+    pub network: Network,
+
+    /// Demand of the approaches at the border of the network
+    pub scenario: Scenario,
+    pub view: View,
+    pub background_texture: Option<Texture2D>,
+    pub car_textures: (Option<Texture2D>, Option<Texture2D>, Option<Texture2D>),
+    pub font: Option<Font>,
+
+    /// Approaches fed by the scenario, by intersection
+    boundaries: Vec<Vec<Direction>>,
+}
+
+impl NetworkApp {
+    pub async fn new(network: Network, scenario: Scenario, assets: AssetLoader) -> Self {
+        let view = View {
+            camera: Camera::fitting(network.size()),
+            ..View::default()
+        };
+        let boundaries = (0..network.intersections.len())
+            .map(|index| network.boundary_approaches(index))
+            .collect();
+
+        Self {
+            background_texture: assets.texture("background.png"),
+            car_textures: (
+                assets.texture("car1.png"),
+                assets.texture("car2.png"),
+                assets.texture("car3.png"),
+            ),
+            font: assets.font("PlaypenSans.ttf"),
+            network,
+            scenario,
+            view,
+            boundaries,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            handle_network_input(&mut self.network, &mut self.view);
+
+            if !self.network.is_paused() {
+                // an origin-destination demand replaces the demand of the scenario
+                if self.network.demand.is_empty() {
+                    for (intersection, boundary) in self.network.intersections.iter_mut().zip(&self.boundaries) {
+                        self.scenario.spawn_from(intersection, boundary);
+                    }
+                }
+                self.network.update();
+            }
+
+            for (index, intersection) in self.network.intersections.iter().enumerate() {
+                set_camera(&self.view.camera.camera_2d_at(self.network.offset(index)));
+                draw_background(self.background_texture.as_ref(), &intersection.geometry);
+
+                if self.view.show_heatmap {
+                    draw_heatmap(&intersection.statistics.heatmap, intersection.params.max_speed);
+                }
+
+                for car in intersection.cars() {
+                    draw_car(car, &self.car_textures);
+                }
+
+                draw_debug(intersection, &self.view.debug);
+            }
+
+            set_default_camera();
+            if self.view.show_hud {
+                draw_network_hud(&self.network, self.font.as_ref());
+            }
+
+            next_frame().await;
+        }
+    }
+}
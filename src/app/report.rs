@@ -0,0 +1,69 @@
+use crate::app::Statistics;
+use crate::traffic::MessageCounts;
+use serde::Serialize;
+use std::path::Path;
+
+/// Summary of a finished run, written as JSON by headless runs
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub seed: u64,
+    pub duration: f64,
+
+    pub car_count: usize,
+    pub completed: usize,
+    pub throughput_per_hour: f64,
+
+    pub max_speed: f32,
+    pub min_speed: f32,
+    pub max_time: f64,
+    pub min_time: f64,
+    pub mean_time: f64,
+
+    pub close_calls: usize,
+    pub collisions: usize,
+
+    /// Messages of the runs with V2I messaging
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<MessageCounts>,
+}
+
+impl Report {
+    pub fn new(statistics: &Statistics, seed: u64, duration: f64) -> Report {
+        let completed = statistics.travel_times.len();
+        let mean_time = if completed == 0 {
+            0.0
+        } else {
+            statistics.travel_times.iter().sum::<f64>() / completed as f64
+        };
+
+        Report {
+            seed,
+            duration,
+            car_count: statistics.car_count,
+            completed,
+            throughput_per_hour: completed as f64 * 3600.0 / duration.max(f64::EPSILON),
+            max_speed: statistics.max_speed,
+            min_speed: statistics.min_speed,
+            max_time: statistics.max_time,
+            min_time: statistics.min_time,
+            mean_time,
+            close_calls: statistics.close_calls.len(),
+            collisions: statistics.collisions.len(),
+            messages: None,
+        }
+    }
+
+    /// Writes the report to `out`, or to stdout when no file is given
+    pub fn write(&self, out: Option<&Path>) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+
+        match out {
+            Some(path) => std::fs::write(path, json)
+                .map_err(|error| format!("cannot write {}: {}", path.display(), error)),
+            None => {
+                println!("{}", json);
+                Ok(())
+            }
+        }
+    }
+}
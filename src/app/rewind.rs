@@ -0,0 +1,247 @@
+use crate::app::{SeriesLengths, Statistics};
+use crate::constant::{REWIND_DURATION, REWIND_JUMP, REWIND_PERIOD};
+use crate::traffic::TrafficState;
+use std::collections::VecDeque;
+
+/// State of the simulation at some time, its statistics keeping only the lengths of their
+/// series, which are shared by every frame
+#[derive(Debug, Clone)]
+struct Frame {
+    state: TrafficState,
+    series_lengths: SeriesLengths,
+}
+
+/// Ring buffer of the last seconds of the simulation, browsed from the timeline
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    frames: VecDeque<Frame>,
+
+    /// Series of the statistics up to the most recent frame
+    series: Statistics,
+
+    /// Frame shown while scrubbing, `None` while the simulation runs
+    cursor: Option<usize>,
+
+    /// Close calls and collisions counted in the last recorded frame
+    incidents: usize,
+
+    /// Simulated times of the close calls and collisions seen in the buffer
+    incident_times: Vec<f64>,
+
+    /// Time of the incident that caused the last jump, so that replaying it does not jump again
+    jumped_at: f64,
+
+    /// Jump back and pause when a close call or a collision is recorded
+    pub auto_jump: bool,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Rewind {
+            frames: VecDeque::new(),
+            series: Statistics::default(),
+            cursor: None,
+            incidents: 0,
+            incident_times: Vec::new(),
+            jumped_at: f64::NEG_INFINITY,
+            auto_jump: true,
+        }
+    }
+}
+
+fn incidents(traffic_state: &TrafficState) -> usize {
+    traffic_state.statistics.close_calls.len() + traffic_state.statistics.collisions.len()
+}
+
+impl Rewind {
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Forgets every frame, for when the simulation is replaced by another one
+    pub fn clear(&mut self) {
+        *self = Rewind {
+            auto_jump: self.auto_jump,
+            ..Rewind::default()
+        };
+    }
+
+    /// Keeps a frame of the running simulation every `REWIND_PERIOD`, and jumps
+    /// back `REWIND_JUMP` seconds if a new incident was recorded since the last call
+    pub fn record(&mut self, traffic_state: &mut TrafficState) {
+        if self.is_scrubbing() {
+            return;
+        }
+
+        if self.frames.back().is_some_and(|frame| traffic_state.time < frame.state.time) {
+            self.clear();
+        }
+        let last_time = self.frames.back().map(|frame| frame.state.time);
+
+        let incident = incidents(traffic_state) > self.incidents && !self.frames.is_empty();
+        self.incidents = incidents(traffic_state);
+
+        if incident || last_time.is_none_or(|last_time| traffic_state.time >= last_time + REWIND_PERIOD - 1e-9) {
+            self.push(traffic_state);
+        }
+
+        let oldest = traffic_state.time - REWIND_DURATION;
+        while self.frames.front().is_some_and(|frame| frame.state.time < oldest) {
+            self.frames.pop_front();
+        }
+        self.incident_times.retain(|&time| time >= oldest);
+
+        if incident {
+            self.incident_times.push(traffic_state.time);
+            if self.auto_jump && traffic_state.time > self.jumped_at + 1e-9 {
+                self.jumped_at = traffic_state.time;
+                self.seek(traffic_state.time - REWIND_JUMP, traffic_state);
+            }
+        }
+    }
+
+    /// Pauses on the most recent frame
+    pub fn start(&mut self, traffic_state: &mut TrafficState) {
+        if self.is_scrubbing() {
+            return;
+        }
+
+        if self.frames.back().is_none_or(|frame| frame.state.time < traffic_state.time) {
+            self.push(traffic_state);
+        }
+        self.show(self.frames.len() - 1, traffic_state);
+    }
+
+    pub fn step_back(&mut self, traffic_state: &mut TrafficState) {
+        self.start(traffic_state);
+        if let Some(cursor) = self.cursor {
+            self.show(cursor.saturating_sub(1), traffic_state);
+        }
+    }
+
+    pub fn step_forward(&mut self, traffic_state: &mut TrafficState) {
+        if let Some(cursor) = self.cursor {
+            self.show((cursor + 1).min(self.frames.len() - 1), traffic_state);
+        }
+    }
+
+    /// Shows the last frame recorded at or before `time`
+    pub fn seek(&mut self, time: f64, traffic_state: &mut TrafficState) {
+        self.start(traffic_state);
+        let index = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.state.time <= time)
+            .unwrap_or(0);
+        self.show(index, traffic_state);
+    }
+
+    /// Continues the simulation from the shown frame, dropping the frames after it
+    pub fn resume(&mut self, traffic_state: &TrafficState) {
+        if let Some(cursor) = self.cursor.take() {
+            self.frames.truncate(cursor + 1);
+            self.series.truncate_series(traffic_state.statistics.series_lengths());
+            self.incidents = incidents(traffic_state);
+            self.incident_times.retain(|&time| time <= traffic_state.time);
+        }
+    }
+
+    /// Shows the most recent frame and continues from it
+    pub fn resume_live(&mut self, traffic_state: &mut TrafficState) {
+        if self.is_scrubbing() {
+            self.show(self.frames.len() - 1, traffic_state);
+            self.resume(traffic_state);
+        }
+    }
+
+    /// Keeps a frame of `traffic_state` without cloning the series of its statistics
+    fn push(&mut self, traffic_state: &mut TrafficState) {
+        self.series.extend_series(&traffic_state.statistics);
+
+        let series = traffic_state.statistics.take_series();
+        let state = traffic_state.clone();
+        let series_lengths = series.series_lengths();
+        traffic_state.statistics.put_series(series);
+
+        self.frames.push_back(Frame { state, series_lengths });
+    }
+
+    fn show(&mut self, index: usize, traffic_state: &mut TrafficState) {
+        if let Some(frame) = self.frames.get(index) {
+            *traffic_state = frame.state.clone();
+            traffic_state.statistics.extend_series(&self.series);
+            traffic_state.statistics.truncate_series(frame.series_lengths);
+            self.cursor = Some(index);
+        }
+    }
+
+    /// Simulated times of the oldest and the most recent frames
+    pub fn span(&self) -> Option<(f64, f64)> {
+        Some((self.frames.front()?.state.time, self.frames.back()?.state.time))
+    }
+
+    pub fn incident_times(&self) -> &[f64] {
+        &self.incident_times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::TICK;
+
+    /// Ten simulated seconds of random traffic, with the statistics seen at each tick
+    fn play(rewind: &mut Rewind, traffic_state: &mut TrafficState) -> Vec<(f64, SeriesLengths)> {
+        let mut seen = Vec::new();
+        for tick in 0..(10.0 / TICK) as usize {
+            if tick % 20 == 0 {
+                traffic_state.gen_car_random();
+            }
+            traffic_state.update();
+            rewind.record(traffic_state);
+            seen.push((traffic_state.time, traffic_state.statistics.series_lengths()));
+        }
+        seen
+    }
+
+    #[test]
+    fn frames_share_the_series_of_the_statistics() {
+        let (mut rewind, mut traffic_state) = (Rewind::default(), TrafficState::with_geometry(3, Default::default()));
+        rewind.auto_jump = false;
+        let seen = play(&mut rewind, &mut traffic_state);
+
+        assert!(!traffic_state.statistics.speed_samples.is_empty());
+        assert!(rewind.frames.iter().all(|frame| frame.state.statistics.speed_samples.is_empty()));
+        let last_frame = rewind.frames.back().unwrap().state.time;
+        let (_, lengths) = seen.iter().find(|(time, _)| *time == last_frame).unwrap();
+        assert_eq!(rewind.series.series_lengths(), *lengths);
+
+        rewind.seek(traffic_state.time - 5.0, &mut traffic_state);
+        let (_, lengths) = seen.iter().find(|(time, _)| *time == traffic_state.time).unwrap();
+        assert_eq!(traffic_state.statistics.series_lengths(), *lengths);
+
+        // going back to the most recent frame restores the series up to it
+        rewind.resume_live(&mut traffic_state);
+        let (_, lengths) = seen.iter().find(|(time, _)| *time == traffic_state.time).unwrap();
+        assert_eq!(traffic_state.statistics.series_lengths(), *lengths);
+        assert_eq!(rewind.series.series_lengths(), *lengths);
+    }
+
+    #[test]
+    fn resuming_drops_the_series_after_the_shown_frame() {
+        let (mut rewind, mut traffic_state) = (Rewind::default(), TrafficState::with_geometry(3, Default::default()));
+        rewind.auto_jump = false;
+        play(&mut rewind, &mut traffic_state);
+
+        rewind.seek(traffic_state.time - 5.0, &mut traffic_state);
+        rewind.resume(&traffic_state);
+        assert_eq!(rewind.series.series_lengths(), traffic_state.statistics.series_lengths());
+
+        // the series kept since are those of the continued simulation
+        play(&mut rewind, &mut traffic_state);
+        let statistics = &traffic_state.statistics;
+        assert!(statistics.finish_times.starts_with(&rewind.series.finish_times));
+        assert!(statistics.speed_samples.starts_with(&rewind.series.speed_samples));
+        assert!(rewind.series.speed_samples.len() > statistics.speed_samples.len() / 2);
+    }
+}
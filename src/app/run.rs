@@ -0,0 +1,121 @@
+use crate::app::{Report, Scenario};
+use crate::constant::TICK;
+use crate::draw::save_svg;
+use crate::traffic::{Snapshot, TrafficState};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::path::PathBuf;
+
+/// A scenario played for a fixed simulated duration, with or without a window
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub scenario: Scenario,
+    pub duration: f64,
+    pub seed: u64,
+
+    /// Situation the run starts from, keeping its own driving parameters, on the lanes of
+    /// the scenario as checked by `Snapshot::check`
+    pub initial: Option<Snapshot>,
+}
+
+impl Run {
+    pub fn traffic_state(&self) -> TrafficState {
+        if let Some(snapshot) = &self.initial {
+            let mut traffic_state = TrafficState::restore(snapshot, self.scenario.geometry())
+                .expect("snapshot checked against the lanes of the scenario");
+            traffic_state.rng = StdRng::seed_from_u64(self.seed);
+            self.scenario.configure(&mut traffic_state);
+            return traffic_state;
+        }
+
+        self.scenario.traffic_state(self.seed)
+    }
+
+    /// Simulated time at which the run ends
+    fn end_time(&self) -> f64 {
+        self.initial.as_ref().map_or(0.0, |snapshot| snapshot.time) + self.duration
+    }
+
+    pub fn step(&self, traffic_state: &mut TrafficState) {
+        self.scenario.spawn(traffic_state);
+        traffic_state.update();
+    }
+
+    pub fn is_finished(&self, traffic_state: &TrafficState) -> bool {
+        traffic_state.time >= self.end_time()
+    }
+
+    /// The statistics of a restored run also cover the time before its snapshot
+    pub fn report(&self, traffic_state: &TrafficState) -> Report {
+        let mut report = Report::new(&traffic_state.statistics, self.seed, self.end_time());
+        report.messages = traffic_state.v2i.as_ref().map(|v2i| v2i.counts);
+        report
+    }
+
+    /// Plays the whole scenario as fast as possible, without graphics
+    pub fn play_headless(&self) -> TrafficState {
+        self.play_headless_with(|_| ())
+    }
+
+    /// Same as `play_headless`, calling `observe` before the first step and after every step
+    pub fn play_headless_with(&self, mut observe: impl FnMut(&TrafficState)) -> TrafficState {
+        let mut traffic_state = self.traffic_state();
+        observe(&traffic_state);
+
+        while !self.is_finished(&traffic_state) {
+            self.step(&mut traffic_state);
+            observe(&traffic_state);
+        }
+
+        traffic_state
+    }
+
+    pub fn run_headless(&self) -> Report {
+        self.report(&self.play_headless())
+    }
+}
+
+/// Files written at the end of a run
+#[derive(Debug, Clone, Default)]
+pub struct RunOutputs {
+    /// JSON report, printed to stdout when missing
+    pub report: Option<PathBuf>,
+    pub heatmap: Option<PathBuf>,
+
+    /// Simulated times at which an SVG picture of the intersection is written
+    pub svg_times: Vec<f64>,
+    pub svg_dir: PathBuf,
+
+    /// Snapshot of the final state, to reopen it with `--load`
+    pub save: Option<PathBuf>,
+}
+
+impl RunOutputs {
+    /// Writes the SVG pictures whose time was reached during the last step, or those of the
+    /// start of the run, `--svg-at 0` included, when called before the first step
+    pub fn write_snapshots(&self, traffic_state: &TrafficState) -> Result<(), String> {
+        for &time in &self.svg_times {
+            if traffic_state.time - TICK < time && time <= traffic_state.time {
+                save_svg(traffic_state, &self.svg_dir.join(format!("snapshot_{}s.svg", time)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write(&self, run: &Run, traffic_state: &TrafficState) -> Result<(), String> {
+        run.report(traffic_state).write(self.report.as_deref())?;
+
+        if let Some(path) = &self.save {
+            traffic_state.save(path)?;
+        }
+
+        match &self.heatmap {
+            Some(path) => traffic_state
+                .statistics
+                .heatmap
+                .export_png(path, traffic_state.params.max_speed),
+            None => Ok(()),
+        }
+    }
+}
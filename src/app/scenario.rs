@@ -0,0 +1,214 @@
+use crate::constant::TICK;
+use crate::traffic::{ChannelConfig, Direction, Geometry, LaneChangeConfig, Layout, Params, SensorConfig, TrafficState, V2i};
+use rand::Rng;
+use serde::Deserialize;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Traffic demand of a run, read from a TOML file:
+///
+/// ```toml
+/// [demand] # vehicles per hour arriving on each approach
+/// north = 300
+/// east = 300
+/// south = 300
+/// west = 300
+/// north_east = 300 # and south_east, south_west, north_west, on the legs of a layout with `angles`
+///
+/// [params] # optional, overrides the driving constants
+/// safe_distance = 8
+///
+/// [v2i] # optional, the cars and the intersection then communicate by messages
+/// latency = 0.05 # seconds
+/// jitter = 0.02
+/// loss = 0.1
+///
+/// [perception] # optional, the cars then only see through their sensors
+/// range = 50 # metres
+/// field_of_view = 120 # degrees
+/// position_noise = 0.5 # standard deviations, in metres and m/s
+/// speed_noise = 0.5
+/// reaction_time = 0.3 # seconds
+///
+/// [lane_change] # optional, the cars are then born on any lane and move over to the lane of their movement
+/// politeness = 0.3 # MOBIL model
+/// threshold = 0.1 # m/s²
+/// bias = 1 # m/s²
+/// safe_deceleration = 4 # m/s²
+///
+/// [layout] # optional, lanes of the intersection, as in a network file
+/// lanes = [{ from = "North", movement = "Straight", offset = 6.75 }]
+/// # or roundabout = { radius = 18, lanes = 2 }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub demand: Demand,
+    pub params: Params,
+    pub v2i: Option<ChannelConfig>,
+    pub perception: Option<SensorConfig>,
+    pub lane_change: Option<LaneChangeConfig>,
+    pub layout: Option<Layout>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Demand {
+    pub north: f64,
+    pub east: f64,
+    pub south: f64,
+    pub west: f64,
+    pub north_east: f64,
+    pub south_east: f64,
+    pub south_west: f64,
+    pub north_west: f64,
+}
+
+impl Default for Demand {
+    fn default() -> Self {
+        Demand {
+            north: 300.0,
+            east: 300.0,
+            south: 300.0,
+            west: 300.0,
+            north_east: 300.0,
+            south_east: 300.0,
+            south_west: 300.0,
+            north_west: 300.0,
+        }
+    }
+}
+
+impl Demand {
+    pub fn rate(&self, direction: Direction) -> f64 {
+        match direction {
+            Direction::North => self.north,
+            Direction::East => self.east,
+            Direction::South => self.south,
+            Direction::West => self.west,
+            Direction::NorthEast => self.north_east,
+            Direction::SouthEast => self.south_east,
+            Direction::SouthWest => self.south_west,
+            Direction::NorthWest => self.north_west,
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Scenario, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+
+        let scenario: Scenario =
+            toml::from_str(&content).map_err(|error| format!("invalid scenario {}: {}", path.display(), error))?;
+        if let Some(layout) = &scenario.layout {
+            layout
+                .validate()
+                .map_err(|error| format!("invalid scenario {}: {}", path.display(), error))?;
+        }
+
+        Ok(scenario)
+    }
+
+    /// Sets a demand rate, a channel, sensor or lane change property or a driving parameter from
+    /// its name, `demand` setting every approach at once
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "demand" => {
+                self.demand = Demand {
+                    north: value,
+                    east: value,
+                    south: value,
+                    west: value,
+                    north_east: value,
+                    south_east: value,
+                    south_west: value,
+                    north_west: value,
+                }
+            }
+            "demand.north" => self.demand.north = value,
+            "demand.east" => self.demand.east = value,
+            "demand.south" => self.demand.south = value,
+            "demand.west" => self.demand.west = value,
+            "demand.north_east" => self.demand.north_east = value,
+            "demand.south_east" => self.demand.south_east = value,
+            "demand.south_west" => self.demand.south_west = value,
+            "demand.north_west" => self.demand.north_west = value,
+            "v2i.latency" => self.v2i.get_or_insert_with(ChannelConfig::default).latency = value,
+            "v2i.jitter" => self.v2i.get_or_insert_with(ChannelConfig::default).jitter = value,
+            "v2i.loss" => self.v2i.get_or_insert_with(ChannelConfig::default).loss = value,
+            "perception.range" => self.sensors().range = value as f32,
+            "perception.field_of_view" => self.sensors().field_of_view = value as f32,
+            "perception.position_noise" => self.sensors().position_noise = value as f32,
+            "perception.speed_noise" => self.sensors().speed_noise = value as f32,
+            "perception.reaction_time" => self.sensors().reaction_time = value,
+            "lane_change.politeness" => self.lane_changes().politeness = value as f32,
+            "lane_change.threshold" => self.lane_changes().threshold = value as f32,
+            "lane_change.bias" => self.lane_changes().bias = value as f32,
+            "lane_change.safe_deceleration" => self.lane_changes().safe_deceleration = value as f32,
+            _ => self.params.set(name, value as f32)?,
+        }
+
+        Ok(())
+    }
+
+    /// Empty intersection driven by the parameters, messaging and sensors of the scenario
+    pub fn traffic_state(&self, seed: u64) -> TrafficState {
+        self.traffic_state_with(seed, self.geometry())
+    }
+
+    pub fn geometry(&self) -> Rc<Geometry> {
+        Rc::new(self.layout.as_ref().map(Geometry::new).unwrap_or_default())
+    }
+
+    /// Same as `traffic_state`, on the lanes of `geometry`
+    pub fn traffic_state_with(&self, seed: u64, geometry: Rc<Geometry>) -> TrafficState {
+        let mut traffic_state = TrafficState::with_geometry(seed, geometry);
+        traffic_state.params = self.params;
+        self.configure(&mut traffic_state);
+        traffic_state
+    }
+
+    /// Gives `traffic_state` the messaging, sensors and lane changes set by the scenario,
+    /// keeping its driving parameters and, for a state restored from a snapshot, its own
+    /// configuration where the scenario sets none and the reservations of its messaging
+    pub fn configure(&self, traffic_state: &mut TrafficState) {
+        if let Some(config) = self.v2i {
+            match &mut traffic_state.v2i {
+                Some(v2i) => v2i.config = config,
+                None => traffic_state.v2i = Some(V2i::new(config)),
+            }
+        }
+        if let Some(sensors) = self.perception {
+            traffic_state.sensors = Some(sensors);
+        }
+        if let Some(lane_change) = self.lane_change {
+            traffic_state.lane_change = Some(lane_change);
+        }
+    }
+
+    fn sensors(&mut self) -> &mut SensorConfig {
+        self.perception.get_or_insert_with(SensorConfig::default)
+    }
+
+    fn lane_changes(&mut self) -> &mut LaneChangeConfig {
+        self.lane_change.get_or_insert_with(LaneChangeConfig::default)
+    }
+
+    /// Spawns the cars arriving during the next tick, on every approach with lanes
+    pub fn spawn(&self, traffic_state: &mut TrafficState) {
+        let directions = traffic_state.approaches();
+        self.spawn_from(traffic_state, &directions);
+    }
+
+    /// Same as `spawn`, on the given approaches only
+    pub fn spawn_from(&self, traffic_state: &mut TrafficState, directions: &[Direction]) {
+        for &direction in directions {
+            let probability = self.demand.rate(direction) * TICK / 3600.0;
+
+            if traffic_state.rng.gen_bool(probability.clamp(0.0, 1.0)) {
+                traffic_state.gen_car(direction);
+            }
+        }
+    }
+}
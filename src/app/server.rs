@@ -0,0 +1,190 @@
+use crate::constant::THROUGHPUT_WINDOW;
+use crate::traffic::{CarSnapshot, Direction, TrafficState};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::time::Duration;
+use tungstenite::{Error, Message, WebSocket};
+
+/// Frames waiting for a slow client before the next ones are dropped
+const CLIENT_BACKLOG: usize = 120;
+
+/// How long a client thread waits for a command before sending the pending frames
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+static SERVER: OnceCell<Server> = OnceCell::new();
+
+/// Messages accepted from the clients, mirroring the keyboard controls:
+///
+/// ```json
+/// {"command": "gen_car", "coming_from": "North"}
+/// {"command": "gen_car_random"}
+/// {"command": "toggle_pause"}
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ServerCommand {
+    GenCar { coming_from: Direction },
+    GenCarRandom,
+    TogglePause,
+}
+
+/// State streamed to the clients after every simulation step
+#[derive(Debug, Serialize)]
+struct Frame {
+    time: f64,
+    paused: bool,
+    cars: Vec<CarSnapshot>,
+    statistics: FrameStatistics,
+}
+
+#[derive(Debug, Serialize)]
+struct FrameStatistics {
+    car_count: usize,
+    completed: usize,
+    throughput_per_minute: usize,
+    close_calls: usize,
+    collisions: usize,
+
+    /// Waiting cars on each approach, indexed by `Direction`
+    queues: Vec<usize>,
+}
+
+struct Server {
+    commands: Mutex<Receiver<ServerCommand>>,
+    clients: Mutex<Vec<SyncSender<String>>>,
+
+    /// Time and pause state of the last streamed frame
+    last_frame: Mutex<Option<(f64, bool)>>,
+}
+
+/// Listens on `127.0.0.1:port` for WebSocket clients, until the program exits
+pub fn start_server(port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|error| format!("cannot listen on port {}: {}", port, error))?;
+
+    let (command_sender, commands) = mpsc::channel();
+    let server = Server {
+        commands: Mutex::new(commands),
+        clients: Mutex::new(Vec::new()),
+        last_frame: Mutex::new(None),
+    };
+    SERVER
+        .set(server)
+        .map_err(|_| "the server is already started".to_string())?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (frame_sender, frames) = mpsc::sync_channel(CLIENT_BACKLOG);
+            let command_sender = command_sender.clone();
+
+            std::thread::spawn(move || {
+                if let Err(error) = serve_client(stream, command_sender, frames) {
+                    eprintln!("server: {}", error);
+                }
+            });
+            if let Some(server) = SERVER.get() {
+                server.clients.lock().unwrap().push(frame_sender);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Applies the commands received since the last call, with the keyboard input
+pub fn apply_commands(traffic_state: &mut TrafficState) {
+    let Some(server) = SERVER.get() else {
+        return;
+    };
+
+    for command in server.commands.lock().unwrap().try_iter() {
+        match command {
+            ServerCommand::GenCar { coming_from } => traffic_state.gen_car(coming_from),
+            ServerCommand::GenCarRandom => traffic_state.gen_car_random(),
+            ServerCommand::TogglePause => traffic_state.toggle_pause(),
+        }
+    }
+}
+
+/// Sends the state to the clients, after every step and when the pause changes
+pub fn stream_frame(traffic_state: &TrafficState) {
+    let Some(server) = SERVER.get() else {
+        return;
+    };
+
+    let frame = Some((traffic_state.time, traffic_state.statistics.is_open));
+    let mut last_frame = server.last_frame.lock().unwrap();
+    let mut clients = server.clients.lock().unwrap();
+    if *last_frame == frame || clients.is_empty() {
+        return;
+    }
+    *last_frame = frame;
+
+    let Ok(message) = serde_json::to_string(&Frame::new(traffic_state)) else {
+        return;
+    };
+    clients.retain(|client| !matches!(client.try_send(message.clone()), Err(TrySendError::Disconnected(_))));
+}
+
+fn serve_client(
+    stream: TcpStream,
+    commands: Sender<ServerCommand>,
+    frames: Receiver<String>,
+) -> Result<(), String> {
+    let mut websocket = tungstenite::accept(stream).map_err(|error| format!("handshake failed: {}", error))?;
+    websocket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|error| error.to_string())?;
+
+    loop {
+        for frame in frames.try_iter() {
+            send(&mut websocket, frame)?;
+        }
+
+        match websocket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<ServerCommand>(&text) {
+                Ok(command) => {
+                    let _ = commands.send(command);
+                }
+                Err(error) => send(&mut websocket, serde_json::json!({ "error": error.to_string() }).to_string())?,
+            },
+            Ok(Message::Close(_)) | Err(Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => {}
+            Err(Error::Io(error)) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(error) => return Err(error.to_string()),
+        }
+    }
+}
+
+fn send(websocket: &mut WebSocket<TcpStream>, text: String) -> Result<(), String> {
+    match websocket.send(Message::text(text)) {
+        Ok(()) | Err(Error::ConnectionClosed) => Ok(()),
+        Err(Error::Io(error)) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+impl Frame {
+    fn new(traffic_state: &TrafficState) -> Frame {
+        let statistics = &traffic_state.statistics;
+
+        Frame {
+            time: traffic_state.time,
+            paused: statistics.is_open,
+            cars: traffic_state.cars().map(|car| car.snapshot()).collect(),
+            statistics: FrameStatistics {
+                car_count: statistics.car_count,
+                completed: statistics.finish_times.len(),
+                throughput_per_minute: statistics.throughput(traffic_state.time, THROUGHPUT_WINDOW),
+                close_calls: statistics.close_calls.len(),
+                collisions: statistics.collisions.len(),
+                queues: traffic_state.lines.iter().map(|line| line.queue_length(&traffic_state.params)).collect(),
+            },
+        }
+    }
+}
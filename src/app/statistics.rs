@@ -1,8 +1,9 @@
-use crate::constant::{CAR_SAFE_DISTANCE};
+use crate::app::Heatmap;
+use crate::constant::SAMPLE_PERIOD;
 use crate::traffic::{Car, TrafficState};
-use macroquad::prelude::get_time;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Statistics {
     pub car_count: usize,
     pub max_speed: f32,
@@ -13,18 +14,40 @@ pub struct Statistics {
     pub collisions: Vec<(usize, usize)>,
     pub close_calls: Vec<(usize, usize)>,
 
+    /// Simulated time at which each car left the intersection
+    pub finish_times: Vec<f64>,
+    /// Time spent in the intersection by each car that left it
+    pub travel_times: Vec<f64>,
+
+    /// Queue length per approach, indexed by `Direction`, sampled every `SAMPLE_PERIOD`
+    pub queue_samples: Vec<(f64, Vec<usize>)>,
+    /// Velocity of every car present at each sample
+    pub speed_samples: Vec<f32>,
+
+    pub heatmap: Heatmap,
+
+    #[serde(skip)]
     pub is_open: bool,
 
 }
 
+/// Lengths of the series of `Statistics`, which only grow during a run
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SeriesLengths {
+    collisions: usize,
+    close_calls: usize,
+    finish_times: usize,
+    travel_times: usize,
+    queue_samples: usize,
+    speed_samples: usize,
+}
+
 impl Statistics {
     pub fn update(&mut self, traffic_state: &TrafficState) {
-        let cars = traffic_state
-            .lines
-            .iter()
-            .flat_map(|line| line.path_cars.iter())
-            .flatten()
-            .collect::<Vec<&Car>>();
+        let cars = traffic_state.cars().collect::<Vec<&Car>>();
+
+        self.sample(traffic_state, &cars);
+        self.heatmap.update(&cars);
 
         for (i, car) in cars.iter().enumerate() {
             if car.velocity > self.max_speed {
@@ -45,7 +68,7 @@ impl Statistics {
                 }
 
                 if car.path == other_car.path
-                    && distance < CAR_SAFE_DISTANCE / 2.0
+                    && distance < traffic_state.params.safe_distance / 2.0
                     && !self.close_calls.contains(&(car.id, other_car.id))
                 {
                     self.close_calls.push((car.id, other_car.id));
@@ -53,7 +76,9 @@ impl Statistics {
             }
 
             if car.is_done() {
-                let car_time = get_time() - car.start_time;
+                let car_time = traffic_state.time - car.start_time;
+                self.finish_times.push(traffic_state.time);
+                self.travel_times.push(car_time);
 
                 if car_time > self.max_time {
                     self.max_time = car_time;
@@ -65,4 +90,82 @@ impl Statistics {
             }
         }
     }
+
+    fn sample(&mut self, traffic_state: &TrafficState, cars: &[&Car]) {
+        let last_sample = self.queue_samples.last().map(|(time, _)| *time);
+        if last_sample.is_some_and(|time| traffic_state.time < time + SAMPLE_PERIOD) {
+            return;
+        }
+
+        let queues = traffic_state.lines.iter().map(|line| line.queue_length(&traffic_state.params)).collect();
+        self.queue_samples.push((traffic_state.time, queues));
+        self.speed_samples.extend(cars.iter().map(|car| car.velocity));
+    }
+
+    pub fn series_lengths(&self) -> SeriesLengths {
+        SeriesLengths {
+            collisions: self.collisions.len(),
+            close_calls: self.close_calls.len(),
+            finish_times: self.finish_times.len(),
+            travel_times: self.travel_times.len(),
+            queue_samples: self.queue_samples.len(),
+            speed_samples: self.speed_samples.len(),
+        }
+    }
+
+    /// Appends the entries of the series of `other` past the own ones, `other` having grown from them
+    pub fn extend_series(&mut self, other: &Statistics) {
+        fn extend<T: Clone>(own: &mut Vec<T>, other: &[T]) {
+            own.extend_from_slice(other.get(own.len()..).unwrap_or_default());
+        }
+
+        extend(&mut self.collisions, &other.collisions);
+        extend(&mut self.close_calls, &other.close_calls);
+        extend(&mut self.finish_times, &other.finish_times);
+        extend(&mut self.travel_times, &other.travel_times);
+        extend(&mut self.queue_samples, &other.queue_samples);
+        extend(&mut self.speed_samples, &other.speed_samples);
+    }
+
+    /// Cuts the series back to `lengths`
+    pub fn truncate_series(&mut self, lengths: SeriesLengths) {
+        self.collisions.truncate(lengths.collisions);
+        self.close_calls.truncate(lengths.close_calls);
+        self.finish_times.truncate(lengths.finish_times);
+        self.travel_times.truncate(lengths.travel_times);
+        self.queue_samples.truncate(lengths.queue_samples);
+        self.speed_samples.truncate(lengths.speed_samples);
+    }
+
+    /// Moves the series out, leaving the statistics with empty ones
+    pub fn take_series(&mut self) -> Statistics {
+        Statistics {
+            collisions: std::mem::take(&mut self.collisions),
+            close_calls: std::mem::take(&mut self.close_calls),
+            finish_times: std::mem::take(&mut self.finish_times),
+            travel_times: std::mem::take(&mut self.travel_times),
+            queue_samples: std::mem::take(&mut self.queue_samples),
+            speed_samples: std::mem::take(&mut self.speed_samples),
+            ..Statistics::default()
+        }
+    }
+
+    /// Moves back the series taken by `take_series`
+    pub fn put_series(&mut self, series: Statistics) {
+        self.collisions = series.collisions;
+        self.close_calls = series.close_calls;
+        self.finish_times = series.finish_times;
+        self.travel_times = series.travel_times;
+        self.queue_samples = series.queue_samples;
+        self.speed_samples = series.speed_samples;
+    }
+
+    /// Number of cars that left the intersection during the last `window` seconds
+    pub fn throughput(&self, now: f64, window: f64) -> usize {
+        self.finish_times
+            .iter()
+            .rev()
+            .take_while(|&&time| time > now - window)
+            .count()
+    }
 }
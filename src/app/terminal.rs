@@ -0,0 +1,129 @@
+use crate::app::Scenario;
+use crate::constant::TICK;
+use crate::draw::{render_terminal, terminal_statistics, terminal_status};
+use crate::traffic::{Direction, TrafficState};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const FRAME_DURATION: Duration = Duration::from_millis(50);
+
+/// Most simulation steps played between two frames, so a slow terminal slows the simulation down
+const MAX_STEPS_PER_FRAME: usize = 10;
+
+/// Lines kept under the intersection for the statistics
+const STATUS_LINES: u16 = 2;
+
+/// Text-mode frontend running the same `TrafficState` as the window, in real time, with
+/// the demand of `scenario` if any
+pub fn run_terminal(mut traffic_state: TrafficState, scenario: Option<Scenario>) -> Result<(), String> {
+    let mut stdout = io::stdout();
+
+    terminal::enable_raw_mode().map_err(|error| error.to_string())?;
+    execute!(stdout, EnterAlternateScreen, Hide).map_err(|error| error.to_string())?;
+
+    let result = event_loop(&mut stdout, &mut traffic_state, scenario.as_ref());
+
+    let _ = execute!(stdout, Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+
+    result.map_err(|error| error.to_string())
+}
+
+fn event_loop(stdout: &mut io::Stdout, traffic_state: &mut TrafficState, scenario: Option<&Scenario>) -> io::Result<()> {
+    let mut last_frame = Instant::now();
+    let mut lag = 0.0;
+
+    loop {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !handle_key(traffic_state, key) {
+                    return Ok(());
+                }
+            }
+        }
+
+        #[cfg(feature = "server")]
+        crate::app::apply_commands(traffic_state);
+        #[cfg(feature = "server")]
+        crate::app::stream_frame(traffic_state);
+
+        lag += last_frame.elapsed().as_secs_f64();
+        last_frame = Instant::now();
+
+        if traffic_state.statistics.is_open {
+            lag = 0.0;
+        }
+
+        let mut steps = 0;
+        while lag >= TICK && steps < MAX_STEPS_PER_FRAME {
+            if let Some(scenario) = scenario {
+                scenario.spawn(traffic_state);
+            }
+            traffic_state.update();
+            #[cfg(feature = "server")]
+            crate::app::stream_frame(traffic_state);
+            lag -= TICK;
+            steps += 1;
+        }
+        lag = lag.min(TICK);
+
+        draw(stdout, traffic_state)?;
+        std::thread::sleep(FRAME_DURATION.saturating_sub(last_frame.elapsed()));
+    }
+}
+
+/// Same keys as `handle_input` in the window, returns false to quit
+fn handle_key(traffic_state: &mut TrafficState, key: KeyEvent) -> bool {
+    let quit = key.code == KeyCode::Char('q')
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+    if quit {
+        return false;
+    }
+
+    if traffic_state.statistics.is_open {
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char(' ') | KeyCode::Char('p') => traffic_state.toggle_pause(),
+            _ => {}
+        }
+        return true;
+    }
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('p') => traffic_state.toggle_pause(),
+        KeyCode::Up => traffic_state.gen_car(Direction::South),
+        KeyCode::Down => traffic_state.gen_car(Direction::North),
+        KeyCode::Right => traffic_state.gen_car(Direction::West),
+        KeyCode::Left => traffic_state.gen_car(Direction::East),
+        KeyCode::Char('r') => traffic_state.gen_car_random(),
+        _ => {}
+    }
+
+    true
+}
+
+fn draw(stdout: &mut io::Stdout, traffic_state: &TrafficState) -> io::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let size = (rows.saturating_sub(STATUS_LINES) as usize).min(columns as usize / 2).max(1);
+
+    let lines = if traffic_state.statistics.is_open {
+        terminal_statistics(traffic_state)
+    } else {
+        render_terminal(traffic_state, size)
+            .into_iter()
+            .chain(terminal_status(traffic_state))
+            .collect()
+    };
+
+    queue!(stdout, Clear(ClearType::All))?;
+    for (row, line) in lines.iter().enumerate().take(rows as usize) {
+        queue!(stdout, MoveTo(0, row as u16))?;
+        write!(stdout, "{}", line)?;
+    }
+
+    stdout.flush()
+}
@@ -0,0 +1,74 @@
+use crate::app::Camera;
+
+/// Display options toggled from the keyboard, independent from the simulation
+#[derive(Debug, Clone, Default)]
+pub struct View {
+    pub show_hud: bool,
+    pub show_heatmap: bool,
+    pub chart_page: ChartPage,
+    pub camera: Camera,
+    pub debug: DebugLayers,
+
+    /// Id of the car clicked by the user
+    pub selected_car: Option<usize>,
+}
+
+impl View {
+    pub fn toggle_hud(&mut self) {
+        self.show_hud = !self.show_hud;
+    }
+
+    pub fn toggle_heatmap(&mut self) {
+        self.show_heatmap = !self.show_heatmap;
+    }
+}
+
+/// Diagnostic overlays drawn over the scene, toggled with the number keys
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DebugLayers {
+    pub paths: bool,
+    pub conflicts: bool,
+    pub envelopes: bool,
+    pub leaders: bool,
+    pub intersection: bool,
+    pub status: bool,
+}
+
+/// Pages of the statistics screen, browsed with the arrow keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartPage {
+    #[default]
+    Summary,
+    TravelTime,
+    Throughput,
+    QueueLength,
+    Speed,
+}
+
+impl ChartPage {
+    const ALL: [ChartPage; 5] = [
+        ChartPage::Summary,
+        ChartPage::TravelTime,
+        ChartPage::Throughput,
+        ChartPage::QueueLength,
+        ChartPage::Speed,
+    ];
+
+    pub fn next(self) -> ChartPage {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> ChartPage {
+        Self::ALL[(self as usize + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            ChartPage::Summary => "Statistics",
+            ChartPage::TravelTime => "Travel time",
+            ChartPage::Throughput => "Throughput",
+            ChartPage::QueueLength => "Queue length",
+            ChartPage::Speed => "Speed distribution",
+        }
+    }
+}
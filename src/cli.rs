@@ -0,0 +1,185 @@
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "smart-road", about = "Autonomous vehicles intersection simulation")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Directory of a texture pack replacing the embedded assets
+    #[arg(long, global = true)]
+    pub assets: Option<PathBuf>,
+
+    /// Port of a local WebSocket server streaming the simulation and accepting commands, with
+    /// the window or the terminal of a single intersection
+    #[cfg(feature = "server")]
+    #[arg(long, global = true)]
+    pub serve: Option<u16>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Open the interactive simulation (default)
+    Gui(GuiArgs),
+    /// Run the simulation in the terminal, for machines without a display
+    Tui(TuiArgs),
+    /// Play a scenario for a fixed duration and write its statistics
+    Run(RunArgs),
+    /// Play every combination of a parameter grid over several seeds
+    Sweep(SweepArgs),
+    /// Reinforcement learning environment driven by JSON lines on stdin and stdout
+    Env(EnvArgs),
+}
+
+impl Command {
+    /// Whether the command shows a single intersection, whose frontend applies the commands
+    /// of the server and streams its frames
+    #[cfg(feature = "server")]
+    pub fn is_served(&self) -> bool {
+        match self {
+            Command::Gui(args) => args.grid.is_none() && args.network.is_none(),
+            Command::Tui(_) => true,
+            Command::Run(args) => !args.headless,
+            Command::Sweep(_) | Command::Env(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Args)]
+#[command(group(ArgGroup::new("network_kind").args(["grid", "network"])))]
+pub struct GuiArgs {
+    /// Snapshot to reopen, saved with F5 or `run --save`
+    #[arg(long)]
+    pub load: Option<PathBuf>,
+
+    /// Network of intersections, as `COLUMNSxROWS`
+    #[arg(long, value_parser = parse_grid, conflicts_with_all = ["load", "sumo", "roundabout"])]
+    pub grid: Option<(usize, usize)>,
+
+    /// TOML file describing the intersections and roads of a network
+    #[arg(long, conflicts_with_all = ["load", "grid", "sumo", "roundabout"])]
+    pub network: Option<PathBuf>,
+
+    /// TOML file describing the demand at the border of the network
+    #[arg(long, requires = "network_kind")]
+    pub scenario: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub layout: LayoutArgs,
+}
+
+#[derive(Debug, Default, Args)]
+pub struct TuiArgs {
+    /// TOML file describing the intersection and its traffic demand, the cars being
+    /// otherwise spawned with the keys
+    #[arg(long)]
+    pub scenario: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub layout: LayoutArgs,
+}
+
+/// Junction imported from SUMO or roundabout, replacing the lanes of the intersection
+#[derive(Debug, Default, Args)]
+pub struct LayoutArgs {
+    /// SUMO `.net.xml` file holding the junction
+    #[arg(long)]
+    pub sumo: Option<PathBuf>,
+
+    /// Id of the junction, needed when the file holds several ones
+    #[arg(long, requires = "sumo")]
+    pub junction: Option<String>,
+
+    /// Roundabout with this many circulating lanes, 1 or 2
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2), conflicts_with = "sumo")]
+    pub roundabout: Option<u8>,
+}
+
+fn parse_grid(value: &str) -> Result<(usize, usize), String> {
+    let (columns, rows) = value
+        .split_once('x')
+        .ok_or_else(|| format!("expected COLUMNSxROWS, got `{}`", value))?;
+    let parse = |count: &str| match count.trim().parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("invalid grid size `{}`", count)),
+    };
+
+    Ok((parse(columns)?, parse(rows)?))
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Run without a window, as fast as possible
+    #[arg(long)]
+    pub headless: bool,
+
+    /// TOML file describing the traffic demand
+    #[arg(long)]
+    pub scenario: Option<PathBuf>,
+
+    /// Simulated duration in seconds
+    #[arg(long, default_value_t = 3600.0)]
+    pub duration: f64,
+
+    /// Seed of the random demand, the one saved in the snapshot with `--load`
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// JSON file receiving the statistics, printed to stdout otherwise
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// PNG file receiving the congestion heatmap, one pixel per cell
+    #[arg(long)]
+    pub heatmap: Option<PathBuf>,
+
+    /// Simulated times, in seconds, at which an SVG picture is written
+    #[arg(long, value_delimiter = ',')]
+    pub svg_at: Vec<f64>,
+
+    /// Directory receiving the SVG pictures
+    #[arg(long, default_value = ".")]
+    pub svg_dir: PathBuf,
+
+    /// Snapshot to start from instead of an empty intersection
+    #[arg(long)]
+    pub load: Option<PathBuf>,
+
+    /// File receiving a snapshot of the final state
+    #[arg(long)]
+    pub save: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub layout: LayoutArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct SweepArgs {
+    /// TOML file describing the base scenario and the parameter grid
+    #[arg(long)]
+    pub experiment: PathBuf,
+
+    /// Seed of the first run of each combination, the next ones being incremented
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Worker threads, every available core by default
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// CSV file receiving the aggregated table
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct EnvArgs {
+    /// TOML file describing the episodes, the reward and the scenario
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Seed of the first episode, the next resets incrementing it
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
@@ -1,29 +1,93 @@
 use macroquad::window::Conf;
 
+/// Initial size of the window, in pixels
 pub const WINDOW_SIZE: i32 = 700;
-pub const ROAD_WIDTH: f32 = 270.0;
 
-pub const CAR_WIDTH: f32 = 50.0;
-pub const CAR_LENGTH: f32 = 50.0;
+// World distances are in metres and speeds in metres per second.
+
+/// Side of the square simulated area, centred on the intersection
+pub const WORLD_SIZE: f32 = 70.0;
+pub const ROAD_WIDTH: f32 = 27.0;
+
+pub const CAR_WIDTH: f32 = 5.0;
+pub const CAR_LENGTH: f32 = 5.0;
 
 pub const CAR_PADDING: f32 = (ROAD_WIDTH / 2.0 - CAR_WIDTH) / 4.0;
-pub const CAR_SAFE_DISTANCE: f32 = 100.0;
-pub const MAX_SPEED: f32 = 4.0;
+pub const CAR_SAFE_DISTANCE: f32 = 10.0;
+pub const MAX_SPEED: f32 = 24.0;
+
+pub const MIN_SPEED: f32 = 12.0;
 
-pub const MIN_SPEED: f32 = 2.0;
+/// Speed from which a stopped car starts again
+pub const CAR_START_SPEED: f32 = 6.0;
 
 pub const CAR_ACCELERATION: f32 = 0.1;
 pub const CAR_DECELERATION: f32 = 0.5;
 
+/// Simulated seconds elapsed per call to `TrafficState::update`.
+pub const TICK: f64 = 1.0 / 60.0;
+
+/// Sliding window, in simulated seconds, used for the HUD throughput.
+pub const THROUGHPUT_WINDOW: f64 = 60.0;
+
+/// Interval, in simulated seconds, between two samples of the statistics time series.
+pub const SAMPLE_PERIOD: f64 = 1.0;
+
+/// Side of a cell of the congestion heatmap, in metres.
+pub const HEATMAP_CELL_SIZE: f32 = 1.0;
+
+/// Simulated seconds kept by the rewind buffer of the window.
+pub const REWIND_DURATION: f64 = 30.0;
+
+/// Interval, in simulated seconds, between two frames of the rewind buffer.
+pub const REWIND_PERIOD: f64 = 0.1;
+
+/// Simulated seconds jumped back when a close call or a collision is recorded.
+pub const REWIND_JUMP: f64 = 3.0;
+
+/// Interval, in simulated seconds, between two status messages of a connected car.
+pub const V2I_STATUS_PERIOD: f64 = 0.1;
+
+/// Simulated seconds a connected car waits for a grant before asking again.
+pub const V2I_REQUEST_PERIOD: f64 = 0.5;
+
+/// Distance to the stop line, in metres, from which the first car of a lane asks to cross.
+pub const V2I_REQUEST_DISTANCE: f32 = 15.0;
+
+/// Simulated seconds after which the intersection frees the lane of a car it does not hear from.
+pub const V2I_RESERVATION_TIMEOUT: f64 = 1.0;
+
+/// Largest acceleration, in m/s², of the intelligent driver model weighing lane changes.
+pub const IDM_ACCELERATION: f32 = 1.5;
+
+/// Comfortable deceleration, in m/s², of the same model.
+pub const IDM_DECELERATION: f32 = 2.0;
+
+/// Seconds of headway the same model keeps behind its leader.
+pub const IDM_TIME_GAP: f32 = 1.0;
+
+/// Distance, in metres, over which a car moves over to its new lane.
+pub const LANE_CHANGE_LENGTH: f32 = 10.0;
+
+/// Largest lateral acceleration, in m/s², of the cars in the curves.
+pub const MAX_LATERAL_ACCELERATION: f32 = 3.0;
+
+/// Deceleration, in m/s², of the cars slowing down before a curve.
+pub const CURVE_DECELERATION: f32 = 3.0;
+
+/// Acceleration, in m/s², of the cars speeding up out of a curve.
+pub const CURVE_ACCELERATION: f32 = 2.0;
+
 pub fn window_conf() -> Conf {
     Conf {
         window_title: "smart-road".to_owned(),
         window_width: WINDOW_SIZE,
         window_height: WINDOW_SIZE,
-        window_resizable: false,
+        window_resizable: true,
+        high_dpi: true,
         ..Default::default()
     }
 }
 
 
-pub const STRAIGHT_LENGTH: f32 = (WINDOW_SIZE as f32 - ROAD_WIDTH) / 4.0;
+pub const STRAIGHT_LENGTH: f32 = (WORLD_SIZE - ROAD_WIDTH) / 4.0;
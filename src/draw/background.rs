@@ -1,5 +1,78 @@
+use crate::constant::{ROAD_WIDTH, WORLD_SIZE};
+use crate::traffic::{border_point, outward, Geometry, Roundabout};
 use macroquad::prelude::*;
 
-pub fn draw_background(background_texture: &Texture2D) {
-    draw_texture(background_texture, 0.0, 0.0, WHITE);
+fn road_color() -> Color {
+    Color::from_rgba(58, 58, 58, 255)
+}
+
+fn grass_color() -> Color {
+    Color::from_rgba(79, 121, 66, 255)
+}
+
+pub fn draw_background(background_texture: Option<&Texture2D>, geometry: &Geometry) {
+    match background_texture {
+        _ if !geometry.legs.is_empty() => draw_legs(&geometry.legs),
+        Some(background_texture) => draw_texture_background(background_texture),
+        None => draw_plain_background(),
+    }
+
+    if let Some(roundabout) = &geometry.roundabout {
+        draw_roundabout(roundabout);
+    }
+}
+
+fn draw_texture_background(background_texture: &Texture2D) {
+    draw_texture_ex(
+        background_texture,
+        0.0,
+        0.0,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(vec2(WORLD_SIZE, WORLD_SIZE)),
+            ..Default::default()
+        },
+    );
+}
+
+/// Grass, roads and centre lines, when the background texture is missing
+fn draw_plain_background() {
+    let road_start = (WORLD_SIZE - ROAD_WIDTH) / 2.0;
+    let middle = WORLD_SIZE / 2.0;
+
+    draw_rectangle(0.0, 0.0, WORLD_SIZE, WORLD_SIZE, grass_color());
+    draw_rectangle(road_start, 0.0, ROAD_WIDTH, WORLD_SIZE, road_color());
+    draw_rectangle(0.0, road_start, WORLD_SIZE, ROAD_WIDTH, road_color());
+    draw_line(middle, 0.0, middle, road_start, 0.3, WHITE);
+    draw_line(middle, road_start + ROAD_WIDTH, middle, WORLD_SIZE, 0.3, WHITE);
+    draw_line(0.0, middle, road_start, middle, 0.3, WHITE);
+    draw_line(road_start + ROAD_WIDTH, middle, WORLD_SIZE, middle, 0.3, WHITE);
+}
+
+/// Grass and one road per leg, at the bearings of a junction that is not a square crossing
+fn draw_legs(bearings: &[f32]) {
+    let middle = Vec2::splat(WORLD_SIZE / 2.0);
+
+    draw_rectangle(0.0, 0.0, WORLD_SIZE, WORLD_SIZE, grass_color());
+    draw_circle(middle.x, middle.y, ROAD_WIDTH / 2.0, road_color());
+
+    for &bearing in bearings {
+        let (heading, end) = (outward(bearing), border_point(bearing));
+        let side = vec2(-heading.y, heading.x) * (ROAD_WIDTH / 2.0);
+
+        draw_triangle(middle - side, middle + side, end + side, road_color());
+        draw_triangle(middle - side, end - side, end + side, road_color());
+
+        let line_start = middle + heading * (ROAD_WIDTH / 2.0);
+        draw_line(line_start.x, line_start.y, end.x, end.y, 0.3, WHITE);
+    }
+}
+
+/// Ring and central island over the crossing of the roads
+fn draw_roundabout(roundabout: &Roundabout) {
+    let middle = WORLD_SIZE / 2.0;
+
+    draw_circle(middle, middle, roundabout.outer_edge(), road_color());
+    draw_circle(middle, middle, roundabout.inner_edge(), grass_color());
+    draw_circle_lines(middle, middle, roundabout.inner_edge(), 0.3, WHITE);
 }
@@ -1,13 +1,18 @@
 use macroquad::prelude::*;
 
-pub fn draw_statistics_background(background_statistics_texture: &Texture2D) {
+pub fn draw_statistics_background(background_statistics_texture: Option<&Texture2D>) {
+    let Some(background_statistics_texture) = background_statistics_texture else {
+        clear_background(Color::from_rgba(20, 33, 46, 255));
+        return;
+    };
+
     draw_texture_ex(
         background_statistics_texture,
         0.0,
         0.0,
         WHITE,
         DrawTextureParams {
-            dest_size: Some(vec2(700.0, 700.0)),
+            dest_size: Some(vec2(screen_width(), screen_height())),
             ..Default::default()
         },
     );
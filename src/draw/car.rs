@@ -1,13 +1,17 @@
 use crate::constant::{CAR_LENGTH, CAR_WIDTH};
+use crate::draw::path::going_color;
 use crate::traffic::{Car, Going};
 use macroquad::prelude::*;
 use std::ops::Sub;
 
-pub fn draw_car(car: &Car, car_texture: &(Texture2D, Texture2D, Texture2D)) {
-    let texture = match car.path.going_to {
+pub fn draw_car(
+    car: &Car,
+    car_texture: &(Option<Texture2D>, Option<Texture2D>, Option<Texture2D>),
+) {
+    let texture = match car.movement {
         Going::Straight => &car_texture.0,
-        Going::Right => &car_texture.1,
-        Going::Left => &car_texture.2,
+        Going::Right | Going::SlightRight => &car_texture.1,
+        Going::Left | Going::SlightLeft | Going::UTurn => &car_texture.2,
     };
 
     let move_vector = Vec2::new(
@@ -17,6 +21,22 @@ pub fn draw_car(car: &Car, car_texture: &(Texture2D, Texture2D, Texture2D)) {
 
     let pos = car.pos.sub(move_vector);
 
+    let Some(texture) = texture else {
+        // plain rectangle coloured by movement when the texture is missing
+        draw_rectangle_ex(
+            pos.x,
+            pos.y,
+            CAR_LENGTH,
+            CAR_WIDTH,
+            DrawRectangleParams {
+                rotation: car.rotation,
+                color: going_color(car.movement),
+                ..Default::default()
+            },
+        );
+        return;
+    };
+
     draw_texture_ex(
         texture,
         pos.x,
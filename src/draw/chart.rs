@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+
+const AXIS_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.8);
+const LABEL_SIZE: u16 = 14;
+
+/// Screen rectangle in which a chart is drawn, excluding its axis labels
+#[derive(Debug, Clone, Copy)]
+pub struct ChartArea {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ChartArea {
+    fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Maps a data point to the screen, `(0, 0)` being the bottom left corner
+    fn project(&self, x: f32, y: f32, x_max: f32, y_max: f32) -> Vec2 {
+        vec2(
+            self.x + x / x_max.max(f32::EPSILON) * self.width,
+            self.bottom() - y / y_max.max(f32::EPSILON) * self.height,
+        )
+    }
+}
+
+fn draw_label(text: &str, x: f32, y: f32, font: Option<&Font>) {
+    draw_text_ex(
+        text,
+        x,
+        y,
+        TextParams {
+            font_size: LABEL_SIZE,
+            font,
+            color: AXIS_COLOR,
+            ..Default::default()
+        },
+    );
+}
+
+pub fn draw_axes(area: ChartArea, x_label: &str, y_label: &str, x_max: f32, y_max: f32, font: Option<&Font>) {
+    draw_rectangle(
+        area.x - 50.0,
+        area.y - 30.0,
+        area.width + 70.0,
+        area.height + 75.0,
+        Color::from_rgba(0, 0, 0, 150),
+    );
+    draw_line(area.x, area.y, area.x, area.bottom(), 1.5, AXIS_COLOR);
+    draw_line(area.x, area.bottom(), area.x + area.width, area.bottom(), 1.5, AXIS_COLOR);
+
+    draw_label(y_label, area.x - 40.0, area.y - 12.0, font);
+    draw_label(&format!("{:.0}", y_max), area.x - 40.0, area.y + 5.0, font);
+    draw_label("0", area.x - 15.0, area.bottom(), font);
+
+    let x_max_text = format!("{:.1}", x_max);
+    let x_max_width = measure_text(&x_max_text, font, LABEL_SIZE, 1.0).width;
+    draw_label(&x_max_text, area.x + area.width - x_max_width, area.bottom() + 18.0, font);
+
+    let x_label_width = measure_text(x_label, font, LABEL_SIZE, 1.0).width;
+    draw_label(x_label, area.x + (area.width - x_label_width) / 2.0, area.bottom() + 36.0, font);
+}
+
+/// Splits `values` in `bins` buckets of equal width between 0 and their maximum
+pub fn histogram(values: &[f32], bins: usize) -> (Vec<usize>, f32) {
+    let max = values.iter().copied().fold(0.0, f32::max);
+    let mut counts = vec![0; bins];
+
+    for value in values {
+        let bin = ((value / max.max(f32::EPSILON)) * bins as f32) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+
+    (counts, max)
+}
+
+pub fn draw_bars(area: ChartArea, counts: &[usize], y_max: f32, color: Color) {
+    let bar_width = area.width / counts.len().max(1) as f32;
+
+    for (index, &count) in counts.iter().enumerate() {
+        let top = area.project(0.0, count as f32, 1.0, y_max);
+        draw_rectangle(
+            area.x + index as f32 * bar_width + 1.0,
+            top.y,
+            bar_width - 2.0,
+            area.bottom() - top.y,
+            color,
+        );
+    }
+}
+
+pub fn draw_series(area: ChartArea, points: &[(f32, f32)], x_max: f32, y_max: f32, color: Color) {
+    for window in points.windows(2) {
+        let start = area.project(window[0].0, window[0].1, x_max, y_max);
+        let end = area.project(window[1].0, window[1].1, x_max, y_max);
+        draw_line(start.x, start.y, end.x, end.y, 2.0, color);
+    }
+}
+
+pub fn draw_legend(area: ChartArea, entries: &[(&str, Color)], font: Option<&Font>) {
+    for (index, (name, color)) in entries.iter().enumerate() {
+        let x = area.x + area.width - (entries.len() - index) as f32 * 90.0;
+        draw_rectangle(x, area.y - 26.0, 10.0, 10.0, *color);
+        draw_label(name, x + 14.0, area.y - 16.0, font);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::app::DebugLayers;
+use crate::constant::{CAR_WIDTH, ROAD_WIDTH, WORLD_SIZE};
+use crate::draw::draw_path;
+use crate::traffic::{CarStatus, Path, TrafficState};
+use macroquad::prelude::*;
+
+const CONFLICT_COLOR: Color = Color::new(1.0, 0.2, 0.6, 0.5);
+const ACTIVE_CONFLICT_COLOR: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+const STOP_ENVELOPE_COLOR: Color = Color::new(1.0, 0.2, 0.2, 0.25);
+const SLOW_ENVELOPE_COLOR: Color = Color::new(1.0, 0.6, 0.1, 0.15);
+const LEADER_COLOR: Color = Color::new(0.3, 0.8, 1.0, 0.9);
+const INTERSECTION_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.8);
+
+pub fn status_color(status: CarStatus) -> Color {
+    match status {
+        CarStatus::BeforeTurn => Color::new(0.3, 0.6, 1.0, 0.9),
+        CarStatus::Turning => Color::new(1.0, 0.3, 0.3, 0.9),
+        CarStatus::AfterTurn => Color::new(0.3, 1.0, 0.4, 0.9),
+    }
+}
+
+/// Draws the enabled debug layers in world coordinates, reading the state only
+pub fn draw_debug(traffic_state: &TrafficState, layers: &DebugLayers) {
+    if layers.intersection {
+        let corner = (WORLD_SIZE - ROAD_WIDTH) / 2.0;
+        draw_rectangle_lines(corner, corner, ROAD_WIDTH, ROAD_WIDTH, 0.3, INTERSECTION_COLOR);
+    }
+
+    if layers.paths {
+        traffic_state.paths().for_each(|path| draw_path(path));
+    }
+
+    if layers.conflicts {
+        let is_turning_on = |path: &Path| {
+            traffic_state.lines[path.coming_from as usize]
+                .path_cars(path)
+                .iter()
+                .any(|car| car.get_status() == CarStatus::Turning)
+        };
+
+        for conflict in &traffic_state.geometry.conflicts {
+            // both paths have a car inside the intersection
+            let (color, radius) = if is_turning_on(&conflict.paths.0) && is_turning_on(&conflict.paths.1) {
+                (ACTIVE_CONFLICT_COLOR, 0.6)
+            } else {
+                (CONFLICT_COLOR, 0.4)
+            };
+            draw_circle(conflict.point.x, conflict.point.y, radius, color);
+        }
+    }
+
+    let safe_distance = traffic_state.params.safe_distance;
+
+    for line in traffic_state.lines.iter() {
+        for cars in line.path_cars.iter() {
+            for (index, car) in cars.iter().enumerate() {
+                // same thresholds as `Car::update_velocity` on the distance to the leader
+                if layers.envelopes {
+                    for (length, color) in [
+                        (safe_distance * 3.0, SLOW_ENVELOPE_COLOR),
+                        (safe_distance * 2.0, STOP_ENVELOPE_COLOR),
+                    ] {
+                        draw_rectangle_ex(
+                            car.pos.x,
+                            car.pos.y,
+                            length,
+                            CAR_WIDTH,
+                            DrawRectangleParams {
+                                offset: vec2(0.0, 0.5),
+                                rotation: car.rotation,
+                                color,
+                            },
+                        );
+                    }
+                }
+
+                if layers.leaders && index > 0 {
+                    let (from, to) = (car.center(), cars[index - 1].center());
+                    draw_line(from.x, from.y, to.x, to.y, 0.25, LEADER_COLOR);
+                    draw_circle(to.x, to.y, 0.5, LEADER_COLOR);
+                }
+
+                if layers.status {
+                    let center = car.center();
+                    draw_circle(center.x, center.y, CAR_WIDTH / 4.0, status_color(car.get_status()));
+                }
+            }
+        }
+    }
+}
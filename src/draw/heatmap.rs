@@ -0,0 +1,20 @@
+use crate::app::Heatmap;
+use crate::constant::HEATMAP_CELL_SIZE;
+use macroquad::prelude::*;
+
+/// Draws the non empty cells of the heatmap in world coordinates
+pub fn draw_heatmap(heatmap: &Heatmap, max_speed: f32) {
+    for (cell, color) in heatmap.colors(max_speed).into_iter().enumerate() {
+        if color.a == 0.0 {
+            continue;
+        }
+
+        draw_rectangle(
+            (cell % heatmap.columns) as f32 * HEATMAP_CELL_SIZE,
+            (cell / heatmap.columns) as f32 * HEATMAP_CELL_SIZE,
+            HEATMAP_CELL_SIZE,
+            HEATMAP_CELL_SIZE,
+            color,
+        );
+    }
+}
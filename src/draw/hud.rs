@@ -0,0 +1,78 @@
+use crate::constant::THROUGHPUT_WINDOW;
+use crate::traffic::{Network, TrafficState};
+use macroquad::prelude::*;
+
+const HUD_X: f32 = 10.0;
+const HUD_Y: f32 = 10.0;
+const HUD_WIDTH: f32 = 230.0;
+const LINE_HEIGHT: f32 = 18.0;
+const TEXT_SIZE: u16 = 16;
+
+pub fn draw_hud(traffic_state: &TrafficState, font: Option<&Font>) {
+    let statistics = &traffic_state.statistics;
+
+    let messages = [
+        format!("Time: {:.1} s", traffic_state.time),
+        format!("Vehicles: {}", traffic_state.cars().count()),
+        format!(
+            "Throughput: {} / min",
+            statistics.throughput(traffic_state.time, THROUGHPUT_WINDOW)
+        ),
+        format!("Queue {}", traffic_state.queue_summary()),
+        format!("Close calls: {}", statistics.close_calls.len()),
+        format!("Collisions: {}", statistics.collisions.len()),
+    ];
+
+    draw_panel(&messages, font);
+}
+
+/// Totals over the intersections of a network
+pub fn draw_network_hud(network: &Network, font: Option<&Font>) {
+    let intersections = &network.intersections;
+    let total = |count: fn(&TrafficState) -> usize| intersections.iter().map(count).sum::<usize>();
+
+    let time = intersections.first().map_or(0.0, |intersection| intersection.time);
+    let paused = if network.is_paused() { "  (paused)" } else { "" };
+
+    let mut messages = vec![
+        format!("Time: {:.1} s{}", time, paused),
+        format!("Intersections: {}", intersections.len()),
+        format!("Vehicles: {}", total(|intersection| intersection.cars().count())),
+        format!("Transfers: {}", network.transfers),
+        format!("Left the network: {}", network.exits),
+        format!("Close calls: {}", total(|intersection| intersection.statistics.close_calls.len())),
+        format!("Collisions: {}", total(|intersection| intersection.statistics.collisions.len())),
+    ];
+
+    if !network.demand.is_empty() {
+        let trips = &network.trip_times;
+        let mean = trips.iter().sum::<f64>() / trips.len().max(1) as f64;
+        messages.push(format!("Trips completed: {} (mean {:.1} s)", trips.len(), mean));
+    }
+
+    draw_panel(&messages, font);
+}
+
+fn draw_panel(messages: &[String], font: Option<&Font>) {
+    draw_rectangle(
+        HUD_X,
+        HUD_Y,
+        HUD_WIDTH,
+        LINE_HEIGHT * messages.len() as f32 + LINE_HEIGHT / 2.0,
+        Color::from_rgba(0, 0, 0, 160),
+    );
+
+    for (index, message) in messages.iter().enumerate() {
+        draw_text_ex(
+            message,
+            HUD_X + 8.0,
+            HUD_Y + (index as f32 + 1.0) * LINE_HEIGHT,
+            TextParams {
+                font_size: TEXT_SIZE,
+                font,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+    }
+}
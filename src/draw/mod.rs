@@ -1,10 +1,25 @@
 mod background;
 mod background_statistics;
 mod car;
+mod chart;
+mod debug;
+mod heatmap;
+mod hud;
 mod path;
+mod selection;
 mod statistics;
+mod svg;
+mod terminal;
+mod timeline;
 
 pub use background::draw_background;
 pub use car::draw_car;
+pub use debug::draw_debug;
+pub use heatmap::draw_heatmap;
+pub use hud::{draw_hud, draw_network_hud};
 pub use path::draw_path;
+pub use selection::{draw_selected_path, draw_selection_panel};
 pub use statistics::draw_statistics;
+pub use svg::save_svg;
+pub use terminal::{render_terminal, terminal_statistics, terminal_status};
+pub use timeline::{draw_timeline, timeline_area, timeline_time};
@@ -2,17 +2,24 @@ use crate::traffic::{Going, Path};
 use macroquad::color::Color;
 use macroquad::prelude::draw_line;
 
+pub fn going_color(going_to: Going) -> Color {
+    match going_to {
+        Going::Straight => Color::from_rgba(186, 255, 241, 255),
+        Going::Right => Color::from_rgba(253, 233, 171, 255),
+        Going::Left => Color::from_rgba(246, 174, 158, 255),
+        Going::UTurn => Color::from_rgba(214, 190, 245, 255),
+        Going::SlightRight => Color::from_rgba(222, 245, 174, 255),
+        Going::SlightLeft => Color::from_rgba(250, 208, 170, 255),
+    }
+}
+
 pub fn draw_path(path: &Path) {
-    let color = match path.going_to {
-        Going::Straight => Color::from_rgba(186, 255, 241, 0), 
-        Going::Right => Color::from_rgba(253, 233, 171, 0),    
-        Going::Left => Color::from_rgba(246, 174, 158, 0),     
-    };
+    let color = going_color(path.going_to);
 
     let points = path.points();
     for i in 0..(points.len() - 1) {
         let start = points[i];
         let end = points[i + 1];
-        draw_line(start.x, start.y, end.x, end.y, 2.0, color);
+        draw_line(start.x, start.y, end.x, end.y, 0.2, color);
     }
 }
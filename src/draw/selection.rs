@@ -0,0 +1,69 @@
+use crate::constant::CAR_LENGTH;
+use crate::traffic::{Car, TrafficState};
+use macroquad::prelude::*;
+
+const HIGHLIGHT_COLOR: Color = Color::new(1.0, 0.85, 0.2, 0.9);
+const PANEL_WIDTH: f32 = 180.0;
+const LINE_HEIGHT: f32 = 18.0;
+const TEXT_SIZE: u16 = 16;
+
+fn selected_car(traffic_state: &TrafficState, selected_car: usize) -> Option<&Car> {
+    traffic_state.cars().find(|car| car.id == selected_car)
+}
+
+/// Highlights the selected car and its path, in world coordinates
+pub fn draw_selected_path(traffic_state: &TrafficState, selected_car_id: usize) {
+    let Some(car) = selected_car(traffic_state, selected_car_id) else {
+        return;
+    };
+
+    for segment in car.path.points().windows(2) {
+        draw_line(segment[0].x, segment[0].y, segment[1].x, segment[1].y, 0.4, HIGHLIGHT_COLOR);
+    }
+    let center = car.center();
+    draw_circle_lines(center.x, center.y, CAR_LENGTH * 0.7, 0.3, HIGHLIGHT_COLOR);
+}
+
+/// Details of the selected car in a side panel, in screen coordinates
+pub fn draw_selection_panel(traffic_state: &TrafficState, selected_car_id: usize, font: Option<&Font>) {
+    let Some(car) = selected_car(traffic_state, selected_car_id) else {
+        return;
+    };
+
+    let messages = [
+        format!("Car #{}", car.id),
+        format!("From: {:?}", car.path.coming_from),
+        format!("Going: {:?}", car.movement),
+        format!("Speed: {:.1} m/s", car.velocity),
+        format!("Status: {:?}", car.get_status()),
+        format!("Elapsed: {:.1} s", traffic_state.time - car.start_time),
+    ];
+    let perceived = car.perceptions.front().map(|perception| match perception.leader {
+        Some(leader) => format!("Leader: {:.1} m, {:.1} m/s", leader.gap, leader.velocity),
+        None => "Leader: not seen".to_string(),
+    });
+    let messages = messages.into_iter().chain(perceived).collect::<Vec<_>>();
+
+    let x = screen_width() - PANEL_WIDTH - 10.0;
+    draw_rectangle(
+        x,
+        10.0,
+        PANEL_WIDTH,
+        LINE_HEIGHT * messages.len() as f32 + LINE_HEIGHT / 2.0,
+        Color::from_rgba(0, 0, 0, 160),
+    );
+
+    for (index, message) in messages.iter().enumerate() {
+        draw_text_ex(
+            message,
+            x + 8.0,
+            10.0 + (index as f32 + 1.0) * LINE_HEIGHT,
+            TextParams {
+                font_size: TEXT_SIZE,
+                font,
+                color: if index == 0 { HIGHLIGHT_COLOR } else { WHITE },
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -1,11 +1,38 @@
-use crate::app::Statistics;
-use crate::constant::WINDOW_SIZE;
+use crate::app::{ChartPage, Statistics};
+use crate::constant::SAMPLE_PERIOD;
 use crate::draw::background_statistics::draw_statistics_background;
+use crate::draw::chart::*;
+use crate::traffic::Direction;
 use macroquad::prelude::*;
 
+const HISTOGRAM_BINS: usize = 12;
+const THROUGHPUT_BUCKET: f64 = 10.0;
+
+/// Chart area filling the window, with margins for the title and the labels
+fn chart_area() -> ChartArea {
+    ChartArea {
+        x: 100.0,
+        y: 150.0,
+        width: (screen_width() - 160.0).max(100.0),
+        height: (screen_height() - 300.0).max(100.0),
+    }
+}
+
+/// Colour of the queue of each approach, indexed by `Direction`
+const DIRECTION_COLORS: [(&str, Color); 8] = [
+    ("North", Color::new(0.64, 0.98, 0.80, 1.0)),
+    ("East", Color::new(0.99, 0.91, 0.67, 1.0)),
+    ("South", Color::new(0.96, 0.68, 0.62, 1.0)),
+    ("West", Color::new(0.73, 0.80, 1.0, 1.0)),
+    ("NorthEast", Color::new(0.87, 0.96, 0.68, 1.0)),
+    ("SouthEast", Color::new(0.98, 0.82, 0.67, 1.0)),
+    ("SouthWest", Color::new(0.88, 0.70, 0.86, 1.0)),
+    ("NorthWest", Color::new(0.67, 0.92, 0.96, 1.0)),
+];
+
 fn draw_centered_text(text: &str, y: f32, size: f32, color: Color, font: Option<&Font>) {
     let text_width = measure_text(text, font, size as u16, 1.0).width;
-    let x = WINDOW_SIZE as f32 / 2.0 - text_width / 2.0;
+    let x = screen_width() / 2.0 - text_width / 2.0;
     let params = TextParams {
         font_size: size as u16,
         font,
@@ -16,14 +43,40 @@ fn draw_centered_text(text: &str, y: f32, size: f32, color: Color, font: Option<
     draw_text_ex(text, x, y, params);
 }
 
-pub fn draw_statistics(statistics: &Statistics, background: &Texture2D, font: Option<&Font>) {
+/// Statistics screen, the queue chart showing the `approaches` with lanes
+pub fn draw_statistics(
+    statistics: &Statistics,
+    approaches: &[Direction],
+    background: Option<&Texture2D>,
+    font: Option<&Font>,
+    page: ChartPage,
+) {
     draw_statistics_background(background);
+
+    match page {
+        ChartPage::Summary => draw_summary(statistics, font),
+        ChartPage::TravelTime => {
+            let travel_times = statistics.travel_times.iter().map(|&time| time as f32).collect::<Vec<_>>();
+            draw_histogram(&travel_times, "time (s)", font);
+        }
+        ChartPage::Throughput => draw_throughput(statistics, font),
+        ChartPage::QueueLength => draw_queue_length(statistics, approaches, font),
+        ChartPage::Speed => draw_histogram(&statistics.speed_samples, "speed (m/s)", font),
+    }
+
+    if page != ChartPage::Summary {
+        draw_centered_text(page.title(), 80.0, 30.0, Color::from_rgba(163, 250, 203, 225), font);
+    }
+    draw_centered_text("< Left | Right >", screen_height() - 40.0, 16.0, WHITE, font);
+}
+
+fn draw_summary(statistics: &Statistics, font: Option<&Font>) {
     let header_text = "Statistics".to_string();
 
     let messages = [
         format!("Number of Vehicles: {}", statistics.car_count),
-        format!("Max speed: {}", statistics.max_speed),
-        format!("Min speed: {}", statistics.min_speed),
+        format!("Max speed: {:.1} m/s", statistics.max_speed),
+        format!("Min speed: {:.1} m/s", statistics.min_speed),
         format!("Max time: {:.0}", statistics.max_time),
         format!("Min time: {:.0}", statistics.min_time),
         format!("Close calls: {}", statistics.close_calls.len()),
@@ -33,7 +86,7 @@ pub fn draw_statistics(statistics: &Statistics, background: &Texture2D, font: Op
 
     let text_size = 20.0;
     let text_color = WHITE;
-    let text_y_start = WINDOW_SIZE as f32 / 2.5;
+    let text_y_start = screen_height() / 2.5;
     let line_height = 30.0;
 
     draw_centered_text(
@@ -49,3 +102,67 @@ pub fn draw_statistics(statistics: &Statistics, background: &Texture2D, font: Op
         draw_centered_text(stat, y, text_size, text_color, font);
     }
 }
+
+fn draw_histogram(values: &[f32], x_label: &str, font: Option<&Font>) {
+    let (counts, max) = histogram(values, HISTOGRAM_BINS);
+    let y_max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+
+    let area = chart_area();
+    draw_axes(area, x_label, "cars", max, y_max, font);
+    draw_bars(area, &counts, y_max, Color::from_rgba(163, 250, 203, 225));
+}
+
+fn draw_throughput(statistics: &Statistics, font: Option<&Font>) {
+    let buckets = statistics
+        .finish_times
+        .last()
+        .map_or(0, |&last| (last / THROUGHPUT_BUCKET) as usize + 1);
+    let mut counts = vec![0usize; buckets];
+    for &time in statistics.finish_times.iter() {
+        counts[(time / THROUGHPUT_BUCKET) as usize] += 1;
+    }
+
+    // cars per minute at the end of each bucket
+    let per_minute = 60.0 / THROUGHPUT_BUCKET as f32;
+    let points = counts
+        .iter()
+        .enumerate()
+        .map(|(index, &count)| ((index + 1) as f32 * THROUGHPUT_BUCKET as f32, count as f32 * per_minute))
+        .collect::<Vec<_>>();
+
+    let x_max = points.last().map_or(0.0, |point| point.0);
+    let y_max = points.iter().map(|point| point.1).fold(1.0, f32::max);
+
+    let area = chart_area();
+    draw_axes(area, "time (s)", "cars/min", x_max, y_max, font);
+    draw_series(area, &points, x_max, y_max, Color::from_rgba(163, 250, 203, 225));
+}
+
+fn draw_queue_length(statistics: &Statistics, approaches: &[Direction], font: Option<&Font>) {
+    let x_max = statistics
+        .queue_samples
+        .last()
+        .map_or(SAMPLE_PERIOD, |(time, _)| *time) as f32;
+    let y_max = statistics
+        .queue_samples
+        .iter()
+        .flat_map(|(_, queues)| queues.iter())
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+
+    let area = chart_area();
+    draw_axes(area, "time (s)", "cars", x_max, y_max, font);
+
+    let entries = approaches.iter().map(|&direction| DIRECTION_COLORS[direction as usize]).collect::<Vec<_>>();
+    for (&direction, (_, color)) in approaches.iter().zip(&entries) {
+        let points = statistics
+            .queue_samples
+            .iter()
+            .map(|(time, queues)| (*time as f32, queues.get(direction as usize).copied().unwrap_or(0) as f32))
+            .collect::<Vec<_>>();
+        draw_series(area, &points, x_max, y_max, *color);
+    }
+    draw_legend(area, &entries, font);
+}
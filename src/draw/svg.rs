@@ -0,0 +1,116 @@
+use crate::constant::{CAR_LENGTH, CAR_WIDTH, ROAD_WIDTH, WORLD_SIZE};
+use crate::draw::path::going_color;
+use crate::traffic::{border_point, outward, TrafficState};
+use macroquad::color::Color;
+use macroquad::math::Vec2;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Pixels per metre of the exported picture
+const SVG_SCALE: f32 = 10.0;
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _]: [u8; 4] = color.into();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Renders the roads, the lanes and the cars as an SVG document, without any GPU
+fn render_svg(traffic_state: &TrafficState) -> String {
+    let mut svg = String::new();
+    let road_start = (WORLD_SIZE - ROAD_WIDTH) / 2.0;
+    let middle = WORLD_SIZE / 2.0;
+
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {world} {world}">"#,
+        size = WORLD_SIZE * SVG_SCALE,
+        world = WORLD_SIZE,
+    );
+    let _ = writeln!(svg, r##"<rect width="{0}" height="{0}" fill="#4f7942"/>"##, WORLD_SIZE);
+    if traffic_state.geometry.legs.is_empty() {
+        let _ = writeln!(
+            svg,
+            r##"<g fill="#3a3a3a"><rect x="{0}" y="0" width="{1}" height="{2}"/><rect x="0" y="{0}" width="{2}" height="{1}"/></g>"##,
+            road_start, ROAD_WIDTH, WORLD_SIZE,
+        );
+        let _ = writeln!(
+            svg,
+            r##"<g stroke="#ffffff" stroke-width="0.3"><line x1="{0}" y1="0" x2="{0}" y2="{1}"/><line x1="0" y1="{0}" x2="{1}" y2="{0}"/></g>"##,
+            middle, WORLD_SIZE,
+        );
+    } else {
+        let _ = writeln!(svg, r##"<circle cx="{0}" cy="{0}" r="{1}" fill="#3a3a3a"/>"##, middle, ROAD_WIDTH / 2.0);
+        for &bearing in &traffic_state.geometry.legs {
+            let (start, end) = (Vec2::splat(middle) + outward(bearing) * (ROAD_WIDTH / 2.0), border_point(bearing));
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{0}" y1="{0}" x2="{1:.2}" y2="{2:.2}" stroke="#3a3a3a" stroke-width="{3}"/>"##,
+                middle, end.x, end.y, ROAD_WIDTH,
+            );
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="#ffffff" stroke-width="0.3"/>"##,
+                start.x, start.y, end.x, end.y,
+            );
+        }
+    }
+    if let Some(roundabout) = &traffic_state.geometry.roundabout {
+        let _ = writeln!(svg, r##"<circle cx="{0}" cy="{0}" r="{1:.2}" fill="#3a3a3a"/>"##, middle, roundabout.outer_edge());
+        let _ = writeln!(
+            svg,
+            r##"<circle cx="{0}" cy="{0}" r="{1:.2}" fill="#4f7942" stroke="#ffffff" stroke-width="0.3"/>"##,
+            middle,
+            roundabout.inner_edge(),
+        );
+    }
+
+    let _ = writeln!(svg, r#"<g fill="none" stroke-width="0.2" stroke-opacity="0.6">"#);
+    for path in traffic_state.paths() {
+        let points = path
+            .points()
+            .iter()
+            .map(|point| format!("{:.2},{:.2}", point.x, point.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            svg,
+            r#"<polyline stroke="{}" points="{}"/>"#,
+            hex(going_color(path.going_to)),
+            points
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(svg, r##"<g stroke="#000000" stroke-width="0.15">"##);
+    for car in traffic_state.cars() {
+        let center = car.center();
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.2}" y="{:.2}" width="{}" height="{}" rx="0.8" fill="{}" transform="rotate({:.1} {:.2} {:.2})"><title>car {}</title></rect>"#,
+            center.x - CAR_LENGTH / 2.0,
+            center.y - CAR_WIDTH / 2.0,
+            CAR_LENGTH,
+            CAR_WIDTH,
+            hex(going_color(car.movement)),
+            car.rotation.to_degrees(),
+            center.x,
+            center.y,
+            car.id,
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(
+        svg,
+        r##"<text x="1" y="3" font-size="2.5" font-family="sans-serif" fill="#ffffff">t = {:.1} s</text>"##,
+        traffic_state.time
+    );
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+pub fn save_svg(traffic_state: &TrafficState, path: &Path) -> Result<(), String> {
+    std::fs::write(path, render_svg(traffic_state))
+        .map_err(|error| format!("cannot write {}: {}", path.display(), error))
+}
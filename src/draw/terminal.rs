@@ -0,0 +1,217 @@
+use crate::constant::{CAR_WIDTH, ROAD_WIDTH, THROUGHPUT_WINDOW, WORLD_SIZE};
+use crate::draw::path::going_color;
+use crate::traffic::{outward, Direction, TrafficState};
+use crossterm::style::{Color, Stylize};
+use macroquad::math::Vec2;
+use std::f32::consts::FRAC_PI_4;
+
+const GRASS: (char, Color) = (' ', Color::Reset);
+const ROAD: (char, Color) = ('·', Color::DarkGrey);
+
+const MARKING: Color = Color::White;
+
+/// Half width, in metres, of the road drawn around each lane
+const LANE_HALF_WIDTH: f32 = CAR_WIDTH * 0.6;
+
+/// Arrows for the headings, clockwise from east as the y axis points down
+const ARROWS: [char; 8] = ['→', '↘', '↓', '↙', '←', '↖', '↑', '↗'];
+
+/// Renders the intersection on `rows` lines of `2 * rows` characters, terminal
+/// cells being about twice as high as wide
+pub fn render_terminal(traffic_state: &TrafficState, rows: usize) -> Vec<String> {
+    let columns = rows * 2;
+    let cell_width = WORLD_SIZE / columns as f32;
+    let cell_height = WORLD_SIZE / rows as f32;
+
+    // the lanes of the geometry, sampled every half cell, under the roads of the legs
+    let geometry = &traffic_state.geometry;
+    let mut grid = vec![vec![GRASS; columns]; rows];
+    let step = cell_width.min(cell_height) / 2.0;
+    for path in &geometry.paths {
+        for segment in path.points().windows(2) {
+            let samples = (segment[0].distance(segment[1]) / step).ceil().max(1.0) as usize;
+            for sample in 0..=samples {
+                let point = segment[0].lerp(segment[1], sample as f32 / samples as f32);
+                for row in cell_range(point.y, cell_height, rows) {
+                    for column in cell_range(point.x, cell_width, columns) {
+                        let center = Vec2::new((column as f32 + 0.5) * cell_width, (row as f32 + 0.5) * cell_height);
+                        if center.distance(point) <= LANE_HALF_WIDTH {
+                            grid[row][column] = ROAD;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let bearings = match geometry.legs.is_empty() {
+        true => Direction::ALL.map(|direction| direction.bearing()).to_vec(),
+        false => geometry.legs.clone(),
+    };
+    for (row, line) in grid.iter_mut().enumerate() {
+        for (column, cell) in line.iter_mut().enumerate() {
+            let center = Vec2::new((column as f32 + 0.5) * cell_width, (row as f32 + 0.5) * cell_height);
+            if let Some(ground) = leg_ground(center, &bearings, Vec2::new(cell_width, cell_height)) {
+                *cell = ground;
+            }
+
+            let radius = center.distance(Vec2::splat(WORLD_SIZE / 2.0));
+            match &geometry.roundabout {
+                Some(roundabout) if radius <= roundabout.inner_edge() => *cell = GRASS,
+                Some(roundabout) if radius <= roundabout.outer_edge() => *cell = ROAD,
+                _ => {}
+            }
+        }
+    }
+
+    for car in traffic_state.cars() {
+        let center = car.center();
+        let column = (center.x / cell_width).floor();
+        let row = (center.y / cell_height).floor();
+        if column < 0.0 || row < 0.0 || column as usize >= columns || row as usize >= rows {
+            continue;
+        }
+
+        let heading = (car.rotation / FRAC_PI_4).round().rem_euclid(8.0) as usize;
+        let [r, g, b, _]: [u8; 4] = going_color(car.movement).into();
+        grid[row as usize][column as usize] = (ARROWS[heading], Color::Rgb { r, g, b });
+    }
+
+    grid.into_iter()
+        .map(|line| {
+            line.into_iter()
+                .map(|(character, color)| character.with(color).to_string())
+                .collect()
+        })
+        .collect()
+}
+
+/// Road or centre line of the legs at `bearings` under the point `center` of a cell of `size`,
+/// as drawn in the window
+fn leg_ground(center: Vec2, bearings: &[f32], size: Vec2) -> Option<(char, Color)> {
+    let offset = center - Vec2::splat(WORLD_SIZE / 2.0);
+
+    bearings.iter().find_map(|&bearing| {
+        let heading = outward(bearing);
+        let (along, across) = (offset.dot(heading), offset.perp_dot(heading));
+        if along < 0.0 || across.abs() > ROAD_WIDTH / 2.0 {
+            return None;
+        }
+
+        // the centre line, within half a cell across the leg, stops at the junction
+        let half_cell = (heading.y.abs() * size.x + heading.x.abs() * size.y) / 2.0;
+        if along > ROAD_WIDTH / 2.0 && across.abs() <= half_cell {
+            // slope of the line in cells rather than in metres
+            let slope = (heading.y / size.y) / (heading.x / size.x);
+            let marking = match slope {
+                _ if slope.abs() > 2.0 => '│',
+                _ if slope.abs() < 0.5 => '─',
+                _ if slope > 0.0 => '╲',
+                _ => '╱',
+            };
+            return Some((marking, MARKING));
+        }
+
+        Some(ROAD)
+    })
+}
+
+/// Cells of size `cell` along an axis of `count` cells within `LANE_HALF_WIDTH` of `coordinate`
+fn cell_range(coordinate: f32, cell: f32, count: usize) -> std::ops::Range<usize> {
+    let first = ((coordinate - LANE_HALF_WIDTH) / cell).floor().max(0.0) as usize;
+    let last = ((coordinate + LANE_HALF_WIDTH) / cell).floor().max(0.0) as usize;
+    first.min(count)..(last + 1).min(count)
+}
+
+/// Live statistics printed under the intersection
+pub fn terminal_status(traffic_state: &TrafficState) -> Vec<String> {
+    let statistics = &traffic_state.statistics;
+
+    vec![
+        format!(
+            "Time {:.1} s | Vehicles {} | Throughput {} / min | Close calls {} | Collisions {}",
+            traffic_state.time,
+            traffic_state.cars().count(),
+            statistics.throughput(traffic_state.time, THROUGHPUT_WINDOW),
+            statistics.close_calls.len(),
+            statistics.collisions.len(),
+        ),
+        format!(
+            "Queue {} | arrows: spawn  r: random  p/esc: pause  q: quit",
+            traffic_state.queue_summary()
+        ),
+    ]
+}
+
+/// Summary shown while paused, as the statistics screen of the window
+pub fn terminal_statistics(traffic_state: &TrafficState) -> Vec<String> {
+    let statistics = &traffic_state.statistics;
+
+    vec![
+        "Statistics".bold().to_string(),
+        String::new(),
+        format!("Number of Vehicles: {}", statistics.car_count),
+        format!("Max speed: {:.1} m/s", statistics.max_speed),
+        format!("Min speed: {:.1} m/s", statistics.min_speed),
+        format!("Max time: {:.0}", statistics.max_time),
+        format!("Min time: {:.0}", statistics.min_time),
+        format!("Close calls: {}", statistics.close_calls.len()),
+        format!("Collisions: {}", statistics.collisions.len()),
+        String::new(),
+        "p/space: resume  esc/q: quit".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{Geometry, Layout, Roundabout};
+    use std::rc::Rc;
+
+    /// Characters of a line without its escape sequences `ESC [ parameters final-byte`
+    fn strip_escapes(line: &str) -> Vec<char> {
+        let mut characters = Vec::new();
+        let mut chars = line.chars().peekable();
+        while let Some(character) = chars.next() {
+            if character == '\x1b' && chars.next_if_eq(&'[').is_some() {
+                // parameters and intermediate bytes up to the final byte, `m` for the colours
+                for escaped in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&escaped) {
+                        break;
+                    }
+                }
+                continue;
+            }
+            characters.push(character);
+        }
+
+        characters
+    }
+
+    /// Characters of the rendered lines, without their colours
+    fn characters(traffic_state: &TrafficState, rows: usize) -> Vec<Vec<char>> {
+        render_terminal(traffic_state, rows)
+            .iter()
+            .map(|line| strip_escapes(line))
+            .collect()
+    }
+
+    #[test]
+    fn strips_the_whole_colour_sequences_only() {
+        let line = "\x1b[38;2;10;200;30m\x1b[48;5;22mm\x1b[0m=m";
+        assert_eq!(strip_escapes(line), ['m', '=', 'm']);
+    }
+
+    #[test]
+    fn draws_the_lanes_of_the_geometry() {
+        let crossing = characters(&TrafficState::with_geometry(0, Default::default()), 20);
+        assert_eq!(crossing[10][20], ROAD.0);
+        assert_eq!(crossing[0][0], GRASS.0);
+
+        let layout = Layout::roundabout(Roundabout::default());
+        let roundabout = characters(&TrafficState::with_geometry(0, Rc::new(Geometry::new(&layout))), 20);
+        assert_eq!(roundabout[10][20], GRASS.0);
+        assert_eq!(roundabout[0][0], GRASS.0);
+        assert!(roundabout[10].contains(&ROAD.0));
+    }
+}
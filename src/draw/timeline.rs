@@ -0,0 +1,64 @@
+use crate::app::Rewind;
+use crate::traffic::TrafficState;
+use macroquad::prelude::*;
+
+const MARGIN: f32 = 20.0;
+const BAR_HEIGHT: f32 = 14.0;
+const TEXT_SIZE: u16 = 16;
+const INCIDENT_COLOR: Color = Color::new(0.95, 0.3, 0.25, 1.0);
+
+/// Bar of the timeline, in screen coordinates, shared with the click detection
+pub fn timeline_area() -> Rect {
+    Rect::new(
+        MARGIN,
+        screen_height() - MARGIN - BAR_HEIGHT,
+        (screen_width() - 2.0 * MARGIN).max(1.0),
+        BAR_HEIGHT,
+    )
+}
+
+/// Simulated time under the horizontal screen position `x` of the timeline
+pub fn timeline_time(rewind: &Rewind, x: f32) -> Option<f64> {
+    let (start, end) = rewind.span()?;
+    let area = timeline_area();
+    let ratio = ((x - area.x) / area.w).clamp(0.0, 1.0) as f64;
+
+    Some(start + ratio * (end - start))
+}
+
+/// Timeline of the rewind buffer with the incidents and the shown frame
+pub fn draw_timeline(rewind: &Rewind, traffic_state: &TrafficState, font: Option<&Font>) {
+    let Some((start, end)) = rewind.span() else {
+        return;
+    };
+    let area = timeline_area();
+    let x = |time: f64| area.x + area.w * ((time - start) / (end - start).max(f64::EPSILON)) as f32;
+
+    draw_rectangle(area.x, area.y, area.w, area.h, Color::from_rgba(0, 0, 0, 160));
+    draw_rectangle(area.x, area.y, x(traffic_state.time) - area.x, area.h, Color::from_rgba(255, 255, 255, 80));
+
+    for &time in rewind.incident_times() {
+        draw_line(x(time), area.y, x(time), area.y + area.h, 2.0, INCIDENT_COLOR);
+    }
+
+    let cursor = x(traffic_state.time);
+    draw_rectangle(cursor - 2.0, area.y - 4.0, 4.0, area.h + 8.0, WHITE);
+
+    let message = format!(
+        "{:.1} s / {:.1} s   , .  step   Enter  resume here   Backspace  live   J  auto jump {}",
+        traffic_state.time,
+        end,
+        if rewind.auto_jump { "on" } else { "off" },
+    );
+    draw_text_ex(
+        &message,
+        area.x,
+        area.y - 10.0,
+        TextParams {
+            font_size: TEXT_SIZE,
+            font,
+            color: WHITE,
+            ..Default::default()
+        },
+    );
+}
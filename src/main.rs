@@ -1,14 +1,194 @@
 mod app;
+mod cli;
 mod constant;
 mod draw;
 mod traffic;
 
 use app::*;
+use clap::Parser;
+use cli::{Cli, Command, EnvArgs, GuiArgs, LayoutArgs, RunArgs, SweepArgs, TuiArgs};
 use constant::window_conf;
+use std::rc::Rc;
+use traffic::{import_sumo, Geometry, Layout, Network, NetworkFile, Roundabout, Snapshot, TrafficState};
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let mut smart_road = App::new().await;
+fn main() {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Gui(GuiArgs::default()));
 
-    smart_road.run().await;
+    #[cfg(feature = "server")]
+    if let Some(port) = cli.serve {
+        let started = match command.is_served() {
+            true => start_server(port),
+            false => Err("--serve needs the window or the terminal of a single intersection: gui, tui or run without --headless".to_string()),
+        };
+        if let Err(error) = started {
+            eprintln!("smart-road: {}", error);
+            std::process::exit(1);
+        }
+    }
+
+    let assets = AssetLoader { dir: cli.assets };
+
+    let result = match command {
+        Command::Gui(args) => gui(args, assets),
+        Command::Tui(args) => tui(args),
+        Command::Run(args) => run(args, assets),
+        Command::Sweep(args) => sweep(args),
+        Command::Env(args) => env(args),
+    };
+
+    if let Err(error) = result {
+        eprintln!("smart-road: {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn open_window(traffic_state: TrafficState, run: Option<Run>, outputs: RunOutputs, assets: AssetLoader) {
+    macroquad::Window::from_config(window_conf(), async move {
+        let mut smart_road = App::new(traffic_state, run, outputs, assets).await;
+
+        smart_road.run().await;
+    });
+}
+
+fn open_network(network: Network, scenario: Scenario, assets: AssetLoader) {
+    macroquad::Window::from_config(window_conf(), async move {
+        NetworkApp::new(network, scenario, assets).await.run().await;
+    });
+}
+
+fn gui(args: GuiArgs, assets: AssetLoader) -> Result<(), String> {
+    let scenario = match &args.scenario {
+        Some(path) => Scenario::load(path)?,
+        None => Scenario::default(),
+    };
+    let seed: u64 = rand::random();
+
+    if let Some(path) = &args.network {
+        let network = NetworkFile::load(path)?.build(|index, geometry| {
+            scenario.traffic_state_with(seed.wrapping_add(index as u64), geometry)
+        })?;
+        open_network(network, scenario, assets);
+        return Ok(());
+    }
+
+    if let Some((columns, rows)) = args.grid {
+        let network = Network::grid(columns, rows, |index| {
+            scenario.traffic_state(seed.wrapping_add(index as u64))
+        });
+        open_network(network, scenario, assets);
+        return Ok(());
+    }
+
+    let geometry = Rc::new(command_layout(&args.layout)?.map(|layout| Geometry::new(&layout)).unwrap_or_default());
+    let traffic_state = match &args.load {
+        Some(path) => TrafficState::restore(&Snapshot::load(path)?, geometry)?,
+        None => TrafficState::with_geometry(seed, geometry),
+    };
+
+    open_window(traffic_state, None, RunOutputs::default(), assets);
+    Ok(())
+}
+
+/// Intersection of the scenario or of the command line in the terminal, spawning the
+/// demand of the scenario when one is given
+fn tui(args: TuiArgs) -> Result<(), String> {
+    let mut scenario = match &args.scenario {
+        Some(path) => Scenario::load(path)?,
+        None => Scenario::default(),
+    };
+    if let Some(layout) = command_layout(&args.layout)? {
+        scenario.layout = Some(layout);
+    }
+
+    let traffic_state = scenario.traffic_state(rand::random());
+    run_terminal(traffic_state, args.scenario.is_some().then_some(scenario))
+}
+
+/// Lanes of the SUMO junction or of the roundabout given on the command line, if any
+fn command_layout(args: &LayoutArgs) -> Result<Option<Layout>, String> {
+    if let Some(lanes) = args.roundabout {
+        return Ok(Some(Layout::roundabout(Roundabout {
+            lanes: lanes as usize,
+            ..Roundabout::default()
+        })));
+    }
+
+    args.sumo
+        .as_deref()
+        .map(|path| import_sumo(path, args.junction.as_deref()))
+        .transpose()
+}
+
+fn run(args: RunArgs, assets: AssetLoader) -> Result<(), String> {
+    let mut scenario = match &args.scenario {
+        Some(path) => Scenario::load(path)?,
+        None => Scenario::default(),
+    };
+    if let Some(layout) = command_layout(&args.layout)? {
+        scenario.layout = Some(layout);
+    }
+    let initial = args.load.as_deref().map(Snapshot::load).transpose()?;
+    if let Some(snapshot) = &initial {
+        snapshot.check(&scenario.geometry())?;
+    }
+    let run = Run {
+        scenario,
+        duration: args.duration,
+        // a snapshot replays its own future unless another seed is given
+        seed: args
+            .seed
+            .or(initial.as_ref().map(|snapshot| snapshot.seed))
+            .unwrap_or_else(rand::random),
+        initial,
+    };
+
+    let outputs = RunOutputs {
+        report: args.out,
+        heatmap: args.heatmap,
+        svg_times: args.svg_at,
+        svg_dir: args.svg_dir,
+        save: args.save,
+    };
+
+    if args.headless {
+        let mut snapshots = Ok(());
+        let traffic_state = run.play_headless_with(|traffic_state| {
+            if snapshots.is_ok() {
+                snapshots = outputs.write_snapshots(traffic_state);
+            }
+        });
+        snapshots?;
+
+        outputs.write(&run, &traffic_state)
+    } else {
+        open_window(run.traffic_state(), Some(run), outputs, assets);
+        Ok(())
+    }
+}
+
+fn sweep(args: SweepArgs) -> Result<(), String> {
+    let experiment = Experiment::load(&args.experiment)?;
+    let threads = args.threads.unwrap_or_else(|| {
+        std::thread::available_parallelism().map_or(1, |threads| threads.get())
+    });
+
+    let rows = experiment.run(args.seed, threads)?;
+    print!("{}", format_table(&rows));
+
+    match &args.out {
+        Some(path) => std::fs::write(path, format_csv(&rows))
+            .map_err(|error| format!("cannot write {}: {}", path.display(), error)),
+        None => Ok(()),
+    }
+}
+
+fn env(args: EnvArgs) -> Result<(), String> {
+    let config = match &args.config {
+        Some(path) => EnvironmentConfig::load(path)?,
+        None => EnvironmentConfig::default(),
+    };
+
+    let mut environment = Environment::new(config, args.seed);
+    serve_stdio(&mut environment, std::io::stdin().lock(), std::io::stdout().lock())
 }
@@ -1,51 +1,119 @@
 use crate::constant::{
-    CAR_ACCELERATION, CAR_DECELERATION, CAR_LENGTH, CAR_SAFE_DISTANCE,
-    MAX_SPEED, MIN_SPEED, STRAIGHT_LENGTH, WINDOW_SIZE,
+    CAR_LENGTH, CAR_START_SPEED, CURVE_ACCELERATION, CURVE_DECELERATION, LANE_CHANGE_LENGTH, TICK,
 };
 use crate::traffic::car::CarStatus::BeforeTurn;
-use crate::traffic::{Path, TrafficState};
+use crate::traffic::{Path, Perception, TrafficState};
 use macroquad::math::Vec2;
-use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicUsize, Ordering};
 
-static CAR_ID: AtomicUsize = AtomicUsize::new(0);
-
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+/// Approach of a junction, named after the compass point it comes from. Square crossings
+/// use the four sides, other junctions the legs their layout gives a bearing to.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     North = 0,
     East = 1,
     South = 2,
     West = 3,
+    NorthEast = 4,
+    SouthEast = 5,
+    SouthWest = 6,
+    NorthWest = 7,
 }
 
 impl Direction {
-    pub fn destination(&self, going_to: Going) -> Direction {
-        match (self, going_to) {
-            (Direction::North, Going::Straight) => Direction::South,
-            (Direction::North, Going::Left) => Direction::East,
-            (Direction::North, Going::Right) => Direction::West,
-
-            (Direction::East, Going::Straight) => Direction::West,
-            (Direction::East, Going::Left) => Direction::South,
-            (Direction::East, Going::Right) => Direction::North,
-
-            (Direction::South, Going::Straight) => Direction::North,
-            (Direction::South, Going::Left) => Direction::West,
-            (Direction::South, Going::Right) => Direction::East,
-
-            (Direction::West, Going::Straight) => Direction::East,
-            (Direction::West, Going::Left) => Direction::North,
-            (Direction::West, Going::Right) => Direction::South,
+    pub const ALL: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+    /// Every approach, indexed by its value
+    pub const LEGS: [Direction; 8] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    /// Compass bearing of the approach, in degrees clockwise from north
+    pub fn bearing(&self) -> f32 {
+        match self {
+            Direction::North => 0.0,
+            Direction::NorthEast => 45.0,
+            Direction::East => 90.0,
+            Direction::SouthEast => 135.0,
+            Direction::South => 180.0,
+            Direction::SouthWest => 225.0,
+            Direction::West => 270.0,
+            Direction::NorthWest => 315.0,
+        }
+    }
+
+    /// Compass point initials, as shown in the status lines
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Direction::North => "N",
+            Direction::NorthEast => "NE",
+            Direction::East => "E",
+            Direction::SouthEast => "SE",
+            Direction::South => "S",
+            Direction::SouthWest => "SW",
+            Direction::West => "W",
+            Direction::NorthWest => "NW",
         }
     }
+
+    /// Approach whose compass bearing is the nearest to `bearing`
+    pub fn from_bearing(bearing: f32) -> Direction {
+        let index = (bearing.rem_euclid(360.0) / 45.0).round() as usize % 8;
+        [
+            Direction::North,
+            Direction::NorthEast,
+            Direction::East,
+            Direction::SouthEast,
+            Direction::South,
+            Direction::SouthWest,
+            Direction::West,
+            Direction::NorthWest,
+        ][index]
+    }
+
+    pub fn opposite(&self) -> Direction {
+        Direction::from_bearing(self.bearing() + 180.0)
+    }
+
+    /// Approach reached by the movement `going_to` on a junction with compass bearings
+    pub fn destination(&self, going_to: Going) -> Direction {
+        Direction::from_bearing(self.bearing() + 180.0 + going_to.turn())
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum Going {
     Straight = 0,
     Right = 1,
     Left = 2,
+    UTurn = 3,
+    SlightRight = 4,
+    SlightLeft = 5,
+}
+
+impl Going {
+    pub const ALL: [Going; 4] = [Going::Straight, Going::Left, Going::Right, Going::UTurn];
+
+    /// Change of heading of the movement, in degrees clockwise
+    pub fn turn(&self) -> f32 {
+        match self {
+            Going::Straight => 0.0,
+            Going::SlightRight => 45.0,
+            Going::Right => 90.0,
+            Going::SlightLeft => -45.0,
+            Going::Left => -90.0,
+            Going::UTurn => 180.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +122,10 @@ pub struct Car {
 
     pub path: Rc<Path>,
 
+    /// Movement the car wants to make, that of its path unless it was born on another lane
+    /// and has not moved over yet
+    pub movement: Going,
+
     pub velocity: f32,
 
     
@@ -65,44 +137,139 @@ pub struct Car {
     point_index: usize,
 
     pub start_time: f64,
+
+    /// Crossing granted by the intersection manager, with V2I messaging
+    pub granted: bool,
+
+    /// Last perceptions with on-board sensors, the oldest one driving the decisions
+    pub perceptions: VecDeque<Perception>,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum CarStatus {
     BeforeTurn,
     Turning,
     AfterTurn,
 }
 
+/// Serializable state of a car, its path being identified by its lane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarSnapshot {
+    pub id: usize,
+    pub coming_from: Direction,
+    pub going_to: Going,
+    #[serde(default)]
+    pub movement: Option<Going>,
+    pub velocity: f32,
+    pub pos: (f32, f32),
+    pub rotation: f32,
+    pub point_index: usize,
+    pub start_time: f64,
+    #[serde(default)]
+    pub granted: bool,
+
+    /// Perceptions not acted upon yet, with a reaction time
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub perceptions: VecDeque<Perception>,
+}
+
 impl Car {
-    pub fn new(path: Rc<Path>) -> Car {
+    pub fn new(id: usize, path: Rc<Path>, start_time: f64, velocity: f32) -> Car {
         let first_point = path.point(0).unwrap();
+        // facing its first segment, which its sensors watch from the first tick
+        let heading = path.point(1).map_or(Vec2::X, |next| next - first_point);
 
         Self {
-            id: CAR_ID.fetch_add(1, Ordering::SeqCst),
+            id,
+            movement: path.going_to,
             path,
             point_index: 0,
-            start_time: get_time(),
+            start_time,
 
             pos: first_point,
-            rotation: 0.0,
-            velocity: MAX_SPEED,
+            rotation: heading.y.atan2(heading.x),
+            velocity,
+            granted: false,
+            perceptions: VecDeque::new(),
         }
     }
 
+    pub fn snapshot(&self) -> CarSnapshot {
+        CarSnapshot {
+            id: self.id,
+            coming_from: self.path.coming_from,
+            going_to: self.path.going_to,
+            movement: Some(self.movement),
+            velocity: self.velocity,
+            pos: self.pos.into(),
+            rotation: self.rotation,
+            point_index: self.point_index,
+            start_time: self.start_time,
+            granted: self.granted,
+            perceptions: self.perceptions.clone(),
+        }
+    }
+
+    /// Rebuilds a car from its snapshot, `path` being the path of its lane
+    pub fn restore(snapshot: &CarSnapshot, path: Rc<Path>) -> Car {
+        Car {
+            id: snapshot.id,
+            movement: snapshot.movement.unwrap_or(path.going_to),
+            path,
+            velocity: snapshot.velocity,
+            pos: snapshot.pos.into(),
+            rotation: snapshot.rotation,
+            point_index: snapshot.point_index,
+            start_time: snapshot.start_time,
+            granted: snapshot.granted,
+            perceptions: snapshot.perceptions.clone(),
+        }
+    }
+
+    /// Distance travelled from the start of the path, while on the approach
     pub fn border_distance(&self) -> f32 {
-        match self.path.coming_from {
-            Direction::North => self.pos.y,
-            Direction::East => WINDOW_SIZE as f32 - self.pos.x,
-            Direction::South => WINDOW_SIZE as f32 - self.pos.y,
-            Direction::West => self.pos.x,
+        self.path.point(0).map_or(0.0, |start| self.pos.distance(start))
+    }
+
+    /// Distance left to the stop line, at the end of the approach
+    pub fn stop_line_distance(&self) -> f32 {
+        self.path.point(1).map_or(0.0, |stop_line| self.pos.distance(stop_line))
+    }
+
+    /// Middle of the car body, `pos` being its front
+    pub fn center(&self) -> Vec2 {
+        self.pos - Vec2::from_angle(self.rotation) * CAR_LENGTH / 2.0
+    }
+
+    /// Distance from its lane on the approach, while moving over to it after a lane change
+    pub fn lateral_offset(&self) -> f32 {
+        match (self.point_index, self.path.point(0), self.path.point(1)) {
+            (0, Some(start), Some(stop)) => (self.pos - start).perp_dot((stop - start).normalize()).abs(),
+            _ => 0.0,
         }
     }
 
+    pub fn is_moving_over(&self) -> bool {
+        self.lateral_offset() > 0.01
+    }
+
+    pub fn point_index(&self) -> usize {
+        self.point_index
+    }
+
     pub fn get_move_vector(&self) -> Option<Vec2> {
-        let next_point = self.path.point(self.point_index + 1);
+        let next_point = self.path.point(self.point_index + 1)?;
+
+        // moving over to a new lane, towards a point of the lane ahead rather than its stop line
+        if let (true, Some(start)) = (self.is_moving_over(), self.path.point(0)) {
+            let heading = (next_point - start).normalize();
+            let along = (self.pos - start).dot(heading) + LANE_CHANGE_LENGTH;
+            if along < next_point.distance(start) {
+                return Some(start + heading * along - self.pos);
+            }
+        }
 
-        next_point.map(|next_point| next_point - self.pos)
+        Some(next_point - self.pos)
     }
 
     pub fn get_status(&self) -> CarStatus {
@@ -118,8 +285,18 @@ impl Car {
     pub fn update(&mut self, prev_car: Option<&Car>, traffic_state: &TrafficState) {
         if let Some(move_vector) = self.get_move_vector() {
             self.velocity = self.update_velocity(prev_car, traffic_state);
+            if let Some(&target_speed) = traffic_state.external_control.target_speeds.get(&self.id) {
+                self.velocity = self.velocity.min(target_speed.max(0.0));
+            }
+            if let Some(speed_limit) = self.path.speed_limit {
+                self.velocity = self.velocity.min(speed_limit);
+            }
+            self.velocity = self.velocity.min(self.curve_speed(traffic_state.params.lateral_acceleration));
+            let step = self.velocity * TICK as f32;
 
-            if move_vector.length() < self.velocity * 1.0 {
+            if move_vector.length() < step {
+                // born on another lane and unable to move over in time, it follows its lane
+                self.movement = self.path.going_to;
                 self.point_index += 1;
                 self.update(prev_car, traffic_state);
                 return;
@@ -128,50 +305,134 @@ impl Car {
             let move_vector = move_vector.normalize();
 
             self.rotation = move_vector.y.atan2(move_vector.x);
-            self.pos += move_vector * self.velocity;
+            self.pos += move_vector * step;
+        }
+    }
+
+    /// Highest speed from which the car can still slow down to the curve speed of the points
+    /// ahead, and which it can have reached speeding up from the curve speed of those behind
+    pub fn curve_speed(&self, lateral_acceleration: f32) -> f32 {
+        let points = self.path.points();
+        let mut speed = f32::INFINITY;
+
+        let mut distance = 0.0;
+        let mut from = self.pos;
+        for (index, &point) in points.iter().enumerate().skip(self.point_index + 1) {
+            distance += from.distance(point);
+            from = point;
+            let curve_speed = self.path.curve_speed(index, lateral_acceleration);
+            speed = speed.min((curve_speed.powi(2) + 2.0 * CURVE_DECELERATION * distance).sqrt());
         }
+
+        let mut distance = 0.0;
+        let mut from = self.pos;
+        for (index, &point) in points.iter().enumerate().take(self.point_index + 1).rev() {
+            distance += from.distance(point);
+            from = point;
+            let curve_speed = self.path.curve_speed(index, lateral_acceleration);
+            speed = speed.min((curve_speed.powi(2) + 2.0 * CURVE_ACCELERATION * distance).sqrt());
+        }
+
+        speed
     }
 
     pub fn update_velocity(&self, prev_car: Option<&Car>, traffic_state: &TrafficState) -> f32 {
-        let velocity = self.velocity.max(1.0);
+        let params = &traffic_state.params;
+        let velocity = self.velocity.max(CAR_START_SPEED);
+        let perception = self.perceptions.front();
+
+        let leader_gap = match perception {
+            Some(perception) => perception.leader.map(|leader| leader.gap),
+            None => prev_car.map(|prev_car| (prev_car.pos - self.pos).length() - CAR_LENGTH),
+        };
 
-        if let Some(prev_car) = prev_car {
-            let distance = (prev_car.pos - self.pos).length() - CAR_LENGTH;
+        if let Some(distance) = leader_gap {
 
-            if distance < CAR_SAFE_DISTANCE * 2.0{
+            if distance < params.safe_distance * 2.0{
                 return 0.0;
             }
 
-            if distance < CAR_SAFE_DISTANCE * 3.0 {
-                return (velocity * (1.0 - CAR_DECELERATION))
-                    .min(MIN_SPEED);
+            if distance < params.safe_distance * 3.0 {
+                return (velocity * (1.0 - params.deceleration))
+                    .min(params.min_speed);
             }
         }
 
         let status = self.get_status();
 
         if status != BeforeTurn {
-            return (velocity * (1.0 + CAR_ACCELERATION)).min(MAX_SPEED);
+            return (velocity * (1.0 + params.acceleration)).min(params.max_speed);
         }
 
-        let collision_paths = self.path.get_potential_collision_paths(traffic_state);
+        let must_yield = match (&traffic_state.external_control.admitted, &traffic_state.v2i) {
+            (Some(admitted), _) => !admitted.contains(&self.id),
+            (None, Some(_)) => !self.granted,
+            (None, None) => match (perception, &traffic_state.geometry.roundabout) {
+                (Some(perception), _) => perception.crossing_traffic,
+                (None, Some(roundabout)) => !roundabout.circulating(self, &traffic_state.lines, params).is_empty(),
+                (None, None) => !self.path.get_potential_collision_paths(traffic_state).is_empty(),
+            },
+        };
 
-        if !collision_paths.is_empty() {
-            let center_distance = STRAIGHT_LENGTH - self.border_distance();
+        if must_yield {
+            let stop_distance = self.stop_line_distance();
 
-            if center_distance < CAR_SAFE_DISTANCE / 2.0 {
+            if stop_distance < params.safe_distance / 2.0 {
                 return 0.0;
             }
-            if center_distance < CAR_SAFE_DISTANCE{
-                return (velocity * (1.0 - CAR_DECELERATION))
-                    .min(MIN_SPEED);
+            if stop_distance < params.safe_distance{
+                return (velocity * (1.0 - params.deceleration))
+                    .min(params.min_speed);
             }
         }
 
-        (velocity * (1.0 + CAR_ACCELERATION)).min(MIN_SPEED)
+        (velocity * (1.0 + params.acceleration)).min(params.min_speed)
     }
 
     pub fn is_done(&self) -> bool {
         self.path.point(self.point_index + 1).is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::MAX_LATERAL_ACCELERATION;
+    use crate::traffic::layout::Geometry;
+
+    /// Speed cap of a car of the northern lane `going_to` standing on each of its points
+    fn caps(going_to: Going) -> Vec<f32> {
+        let path = Geometry::default()
+            .paths
+            .into_iter()
+            .find(|path| path.lane() == (Direction::North, going_to))
+            .unwrap();
+        let mut car = Car::new(0, path.clone(), 0.0, 0.0);
+
+        (0..path.points().len())
+            .map(|index| {
+                car.point_index = index;
+                car.pos = path.point(index).unwrap();
+                car.curve_speed(MAX_LATERAL_ACCELERATION)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn brakes_before_a_curve_and_speeds_up_after_it() {
+        let caps = caps(Going::Right);
+        let slowest = (0..caps.len()).min_by(|&a, &b| caps[a].total_cmp(&caps[b])).unwrap();
+        assert!(caps[slowest].is_finite());
+
+        // the curvature of the sampled curve wavers by a few centimetres per second around its apex
+        assert!(caps[..=slowest].windows(2).all(|pair| pair[0] >= pair[1] - 0.05));
+        assert!(caps[slowest..].windows(2).all(|pair| pair[0] <= pair[1] + 0.05));
+        assert!(caps[0] > caps[slowest]);
+        assert!(caps[caps.len() - 1] > caps[slowest]);
+    }
+
+    #[test]
+    fn straight_lanes_are_not_capped() {
+        assert!(caps(Going::Straight).iter().all(|cap| *cap == f32::INFINITY));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::traffic::Path;
+use macroquad::math::Vec2;
+use std::rc::Rc;
+
+/// Point where two paths cross
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub point: Vec2,
+    pub paths: (Rc<Path>, Rc<Path>),
+}
+
+/// Intersection point of the segments `[a1, a2]` and `[b1, b2]`, if any
+fn segment_intersection(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> Option<Vec2> {
+    let a = a2 - a1;
+    let b = b2 - b1;
+    let denominator = a.perp_dot(b);
+
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let t = (b1 - a1).perp_dot(b) / denominator;
+    let u = (b1 - a1).perp_dot(a) / denominator;
+
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then(|| a1 + a * t)
+}
+
+/// Returns every crossing between two different paths, computed from their geometry
+pub fn find_conflicts(paths: &[Rc<Path>]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+
+    for (i, path) in paths.iter().enumerate() {
+        for other_path in paths.iter().skip(i + 1) {
+            for a in path.points().windows(2) {
+                for b in other_path.points().windows(2) {
+                    if let Some(point) = segment_intersection(a[0], a[1], b[0], b[1]) {
+                        conflicts.push(Conflict {
+                            point,
+                            paths: (path.clone(), other_path.clone()),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    conflicts
+}
@@ -3,7 +3,6 @@ use macroquad::prelude::Vec2;
 const RESOLUTION: usize = 100;
 
 /// courbe quadratique bezier.
-
 pub fn quadratic_curve(start: Vec2, control: Vec2, end: Vec2) -> Vec<Vec2> {
     let mut points = Vec::with_capacity(RESOLUTION + 1);
     for i in 0..=RESOLUTION {
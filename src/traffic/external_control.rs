@@ -0,0 +1,11 @@
+use std::collections::{HashMap, HashSet};
+
+/// Decisions of an external controller, overriding the built-in intersection rule
+#[derive(Debug, Clone, Default)]
+pub struct ExternalControl {
+    /// When set, cars before the intersection stop at its entrance unless their id is listed
+    pub admitted: Option<HashSet<usize>>,
+
+    /// Speed limits in m/s given to some cars, by id
+    pub target_speeds: HashMap<usize, f32>,
+}
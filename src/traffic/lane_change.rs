@@ -0,0 +1,311 @@
+use crate::constant::{CAR_LENGTH, IDM_ACCELERATION, IDM_DECELERATION, IDM_TIME_GAP};
+use crate::traffic::car::CarStatus::AfterTurn;
+use crate::traffic::path::right_of;
+use crate::traffic::{Going, Line, Params, Path};
+use macroquad::math::Vec2;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+
+/// Lane changes on the approaches, read from the `[lane_change]` table of a scenario. The cars
+/// are then born on any lane with a desired movement, and move over one lane at a time to a
+/// lane allowing it when the MOBIL model lets them, or follow their lane past its stop line.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaneChangeConfig {
+    /// Share of the acceleration lost by the followers weighed against the own gain
+    pub politeness: f32,
+
+    /// Gain of acceleration, in m/s², needed to change lanes
+    pub threshold: f32,
+
+    /// Gain, in m/s², granted to the changes towards a lane allowing the movement of the car
+    pub bias: f32,
+
+    /// Hardest braking, in m/s², a change may impose on the car and its new follower
+    pub safe_deceleration: f32,
+}
+
+impl Default for LaneChangeConfig {
+    fn default() -> Self {
+        LaneChangeConfig {
+            politeness: 0.3,
+            threshold: 0.1,
+            bias: 1.0,
+            safe_deceleration: 4.0,
+        }
+    }
+}
+
+/// Car of an approach as seen by the model, `along` being the distance of its front from the border
+#[derive(Debug, Clone, Copy)]
+struct Vehicle {
+    along: f32,
+    velocity: f32,
+}
+
+/// Acceleration of the intelligent driver model, approaching at `velocity` the vehicle `leader`
+/// whose front is `gap` metres ahead of the own front
+fn acceleration(params: &Params, velocity: f32, leader: Option<(f32, Vehicle)>) -> f32 {
+    let free = 1.0 - (velocity / params.min_speed).powi(4);
+    let interaction = leader.map_or(0.0, |(gap, leader)| {
+        let braking = velocity * (velocity - leader.velocity) / (2.0 * (IDM_ACCELERATION * IDM_DECELERATION).sqrt());
+        let desired = (params.safe_distance + velocity * IDM_TIME_GAP + braking).max(0.0);
+        (desired / (gap - CAR_LENGTH).max(0.1)).powi(2)
+    });
+
+    IDM_ACCELERATION * (free - interaction)
+}
+
+/// Nearest vehicle of `lane` ahead of `along`, with the distance between their fronts
+fn leader_of(lane: &[Vehicle], along: f32) -> Option<(f32, Vehicle)> {
+    lane.iter()
+        .filter(|vehicle| vehicle.along > along)
+        .map(|&vehicle| (vehicle.along - along, vehicle))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// Nearest vehicle of `lane` behind `along`
+fn follower_of(lane: &[Vehicle], along: f32) -> Option<Vehicle> {
+    lane.iter()
+        .filter(|vehicle| vehicle.along < along)
+        .max_by(|a, b| a.along.total_cmp(&b.along))
+        .copied()
+}
+
+impl Line {
+    /// Lanes of the approach from right to left, each given by the indices of the paths
+    /// starting on it, several movements sharing a lane until the stop line
+    fn lanes(&self) -> Vec<Vec<usize>> {
+        let Some(heading) = self.heading() else {
+            return Vec::new();
+        };
+        let lateral = |index: usize| self.paths[index].point(0).map_or(0.0, |start| start.dot(right_of(heading)));
+
+        let mut lanes: Vec<Vec<usize>> = Vec::new();
+        for index in 0..self.paths.len() {
+            match lanes.iter_mut().find(|lane| self.paths[lane[0]].point(0) == self.paths[index].point(0)) {
+                Some(lane) => lane.push(index),
+                None => lanes.push(vec![index]),
+            }
+        }
+        lanes.sort_by(|a, b| lateral(b[0]).total_cmp(&lateral(a[0])));
+
+        lanes
+    }
+
+    /// Direction of the approach, from the border to the stop line
+    fn heading(&self) -> Option<Vec2> {
+        let path = self.paths.first()?;
+        Some((path.point(1)? - path.point(0)?).normalize())
+    }
+
+    fn allows(&self, lane: &[usize], movement: Going) -> bool {
+        lane.iter().any(|&index| self.paths[index].going_to == movement)
+    }
+
+    /// Moves over the cars born on a lane that does not allow their movement, one lane per tick
+    /// at most, when the gain of the car and its neighbours outweighs the threshold of `config`
+    pub fn change_lanes(&mut self, config: &LaneChangeConfig, params: &Params) {
+        let (Some(heading), Some(start)) = (self.heading(), self.paths.first().and_then(|path| path.point(0))) else {
+            return;
+        };
+        let along = |point: Vec2| (point - start).dot(heading);
+        let lanes = self.lanes();
+        let lane_of = |path: usize| lanes.iter().position(|lane| lane.contains(&path)).unwrap_or(0);
+
+        let candidates = self
+            .path_cars
+            .iter()
+            .enumerate()
+            .flat_map(|(path, cars)| cars.iter().map(move |car| (path, car)))
+            .filter(|(path, car)| car.point_index() == 0 && !self.allows(&lanes[lane_of(*path)], car.movement))
+            .map(|(_, car)| car.id)
+            .collect::<Vec<_>>();
+
+        for id in candidates {
+            let Some((path, index)) = self
+                .path_cars
+                .iter()
+                .enumerate()
+                .find_map(|(path, cars)| cars.iter().position(|car| car.id == id).map(|index| (path, index)))
+            else {
+                continue;
+            };
+            let car = &self.path_cars[path][index];
+            let current = lane_of(path);
+            let Some(target) = (0..lanes.len())
+                .filter(|&lane| self.allows(&lanes[lane], car.movement))
+                .min_by_key(|&lane| lane.abs_diff(current))
+            else {
+                continue;
+            };
+            let next = if target > current { current + 1 } else { current - 1 };
+
+            // cars of a lane until the end of their turn, and a stopped car past the stop line
+            // of the lanes that do not allow the movement
+            let vehicles = |lane: &[usize], obstacle: bool| {
+                let mut vehicles = lane
+                    .iter()
+                    .flat_map(|&path| &self.path_cars[path])
+                    .filter(|other| other.id != id && other.get_status() != AfterTurn)
+                    .map(|other| Vehicle {
+                        along: along(other.pos),
+                        velocity: other.velocity,
+                    })
+                    .collect::<Vec<_>>();
+                if obstacle {
+                    let stop = self.paths[lane[0]].point(1).map_or(0.0, along);
+                    vehicles.push(Vehicle {
+                        along: stop + CAR_LENGTH,
+                        velocity: 0.0,
+                    });
+                }
+                vehicles
+            };
+            let own = Vehicle {
+                along: along(car.pos),
+                velocity: car.velocity,
+            };
+
+            // too close to the stop line to move over, the car follows its lane
+            let stop = self.paths[path].point(1).map_or(0.0, along);
+            if stop - own.along < CAR_LENGTH {
+                continue;
+            }
+
+            let (old_lane, new_lane) = (vehicles(&lanes[current], false), vehicles(&lanes[next], false));
+            if new_lane.iter().any(|other| (other.along - own.along).abs() < CAR_LENGTH) {
+                continue;
+            }
+
+            let old_ahead = leader_of(&vehicles(&lanes[current], true), own.along);
+            let new_ahead = leader_of(&vehicles(&lanes[next], !self.allows(&lanes[next], car.movement)), own.along);
+            let new_leader = leader_of(&new_lane, own.along);
+            let old_leader = leader_of(&old_lane, own.along);
+
+            // MOBIL: safe for the car and its new follower, and worth the loss of both followers
+            let gain = acceleration(params, own.velocity, new_ahead) - acceleration(params, own.velocity, old_ahead);
+            let mut loss = 0.0;
+            if acceleration(params, own.velocity, new_leader) < -config.safe_deceleration {
+                continue;
+            }
+            if let Some(follower) = follower_of(&new_lane, own.along) {
+                let after = acceleration(params, follower.velocity, Some((own.along - follower.along, own)));
+                if after < -config.safe_deceleration {
+                    continue;
+                }
+                let before = new_leader.map(|(gap, leader)| (gap + own.along - follower.along, leader));
+                loss += acceleration(params, follower.velocity, before) - after;
+            }
+            if let Some(follower) = follower_of(&old_lane, own.along) {
+                let before = acceleration(params, follower.velocity, Some((own.along - follower.along, own)));
+                let after = old_leader.map(|(gap, leader)| (gap + own.along - follower.along, leader));
+                loss += before - acceleration(params, follower.velocity, after);
+            }
+            if gain - config.politeness * loss <= config.threshold - config.bias {
+                continue;
+            }
+
+            let mut car = self.path_cars[path].remove(index);
+            let new_path = lanes[next]
+                .iter()
+                .copied()
+                .find(|&path| self.paths[path].going_to == car.movement)
+                .unwrap_or(lanes[next][0]);
+            car.path = self.paths[new_path].clone();
+            car.granted = false;
+
+            let cars = &mut self.path_cars[new_path];
+            let position = cars.iter().position(|other| along(other.pos) < own.along).unwrap_or(cars.len());
+            cars.insert(position, car);
+        }
+    }
+
+    /// Path of a car wanting `movement` born at the start of `path`: the path of its movement
+    /// when the lane allows it, `path` otherwise
+    pub fn path_on_lane(&self, path: &Rc<Path>, movement: Going) -> Rc<Path> {
+        self.paths
+            .iter()
+            .find(|other| other.going_to == movement && other.point(0) == path.point(0))
+            .unwrap_or(path)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::layout::Geometry;
+    use crate::traffic::{Car, Direction};
+
+    /// Northern approach of the square crossing, its lanes turning right, going straight and turning left
+    fn line() -> Line {
+        let paths = Geometry::default()
+            .paths
+            .into_iter()
+            .filter(|path| path.coming_from == Direction::North)
+            .collect();
+        Line::new(paths)
+    }
+
+    /// Adds to the lane of `going_to` a car wanting `movement`, `along` metres from the border
+    fn add(line: &mut Line, id: usize, going_to: Going, movement: Going, along: f32, velocity: f32) {
+        let path = line.paths.iter().position(|path| path.going_to == going_to).unwrap();
+        let mut car = Car::new(id, line.paths[path].clone(), 0.0, velocity);
+        car.movement = movement;
+        car.pos += line.heading().unwrap() * along;
+        line.path_cars[path].push(car);
+    }
+
+    fn movement_of_lane(line: &Line, id: usize) -> Going {
+        line.paths
+            .iter()
+            .zip(&line.path_cars)
+            .find(|(_, cars)| cars.iter().any(|car| car.id == id))
+            .map(|(path, _)| path.going_to)
+            .unwrap()
+    }
+
+    #[test]
+    fn moves_over_to_a_free_lane_allowing_the_movement() {
+        let mut line = line();
+        add(&mut line, 0, Going::Right, Going::Straight, 2.0, 5.0);
+
+        line.change_lanes(&LaneChangeConfig::default(), &Params::default());
+        assert_eq!(movement_of_lane(&line, 0), Going::Straight);
+    }
+
+    #[test]
+    fn does_not_cut_in_front_of_a_close_follower() {
+        let lines = || {
+            let mut line = line();
+            add(&mut line, 0, Going::Right, Going::Straight, 2.0, 5.0);
+            add(&mut line, 1, Going::Straight, Going::Straight, 2.0 - CAR_LENGTH - 0.5, 10.0);
+            line
+        };
+
+        let mut line = lines();
+        line.change_lanes(&LaneChangeConfig::default(), &Params::default());
+        assert_eq!(movement_of_lane(&line, 0), Going::Right);
+
+        // the same change, once any braking is deemed safe and the follower is not minded
+        let reckless = LaneChangeConfig {
+            politeness: 0.0,
+            safe_deceleration: f32::INFINITY,
+            ..Default::default()
+        };
+        let mut line = lines();
+        line.change_lanes(&reckless, &Params::default());
+        assert_eq!(movement_of_lane(&line, 0), Going::Straight);
+    }
+
+    #[test]
+    fn follows_its_lane_near_the_stop_line() {
+        let mut line = line();
+        let stop = line.paths[0].point(0).unwrap().distance(line.paths[0].point(1).unwrap());
+        add(&mut line, 0, Going::Right, Going::Straight, stop - CAR_LENGTH / 2.0, 5.0);
+
+        line.change_lanes(&LaneChangeConfig::default(), &Params::default());
+        assert_eq!(movement_of_lane(&line, 0), Going::Right);
+    }
+}
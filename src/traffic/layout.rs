@@ -0,0 +1,375 @@
+use crate::constant::{CAR_PADDING, ROAD_WIDTH, STRAIGHT_LENGTH};
+use crate::traffic::{find_conflicts, Conflict, Direction, Going, Path, Roundabout};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Lane identified by its approach and its movement
+pub type Lane = (Direction, Going);
+
+/// Lanes of an intersection, as described in a network file:
+///
+/// ```toml
+/// approach_length = 10.75 # straight part of the lanes before and after their turn, metres
+///
+/// [[lanes]]
+/// from = "North"          # approach of the lane
+/// movement = "Left"       # Straight, Left, Right, UTurn, SlightLeft or SlightRight, one per approach
+/// offset = 1.5            # distance from the centre of the road to the lane, on its right, metres
+/// exit_offset = 1.5       # optional, same distance on the exit road, `offset` by default
+/// speed_limit = 12        # optional, m/s
+/// curvature = 0.1         # optional, how far the turn bends away from the centre
+///
+/// [[lanes]]               # lane given by its points instead, as imported from SUMO
+/// from = "East"
+/// movement = "Straight"
+/// points = [[70, 28], [59, 28], [11, 28], [0, 28]]
+///
+/// [angles]                # optional, bearing of each leg in degrees clockwise from north
+/// North = 0               # the junction then has exactly these legs, from 3 to 8 of the
+/// East = 72               # compass points, and each lane leads to the leg the closest to
+/// SouthEast = 144         # its movement: SlightRight and SlightLeft turn by 45 degrees,
+/// SouthWest = 216         # Right and Left by 90
+/// West = 288
+/// ```
+///
+/// or by a `roundabout` table generating every lane instead, see `Roundabout`. Without
+/// `angles`, the legs are the sides of a square crossing. Lanes of an approach with the same
+/// `offset` start at the same point and make one lane allowing several movements.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Layout {
+    #[serde(default = "default_approach_length")]
+    pub approach_length: f32,
+    #[serde(default)]
+    pub lanes: Vec<LaneLayout>,
+    pub roundabout: Option<Roundabout>,
+    #[serde(default)]
+    pub angles: HashMap<Direction, f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LaneLayout {
+    pub from: Direction,
+    pub movement: Going,
+    #[serde(default)]
+    pub offset: f32,
+    pub exit_offset: Option<f32>,
+    pub speed_limit: Option<f32>,
+    pub curvature: Option<f32>,
+
+    /// Polyline in metres, its first segment ending at the stop line and its last one
+    /// leaving the intersection, replacing the offsets and the curvature
+    pub points: Option<Vec<[f32; 2]>>,
+}
+
+fn default_approach_length() -> f32 {
+    STRAIGHT_LENGTH
+}
+
+impl Default for Layout {
+    /// Four approaches with a right, a straight and a left lane each
+    fn default() -> Self {
+        let offset = |movement| match movement {
+            Going::Right | Going::SlightRight => ROAD_WIDTH / 2.0 - CAR_PADDING,
+            Going::Straight => ROAD_WIDTH / 4.0,
+            Going::Left | Going::SlightLeft | Going::UTurn => CAR_PADDING,
+        };
+
+        Layout {
+            approach_length: STRAIGHT_LENGTH,
+            lanes: Direction::ALL
+                .into_iter()
+                .flat_map(|from| {
+                    [Going::Straight, Going::Left, Going::Right].map(|movement| LaneLayout {
+                        from,
+                        movement,
+                        offset: offset(movement),
+                        exit_offset: None,
+                        speed_limit: None,
+                        curvature: None,
+                        points: None,
+                    })
+                })
+                .collect(),
+            roundabout: None,
+            angles: HashMap::new(),
+        }
+    }
+}
+
+impl Layout {
+    pub fn roundabout(roundabout: Roundabout) -> Layout {
+        Layout {
+            approach_length: STRAIGHT_LENGTH,
+            lanes: Vec::new(),
+            roundabout: Some(roundabout),
+            angles: HashMap::new(),
+        }
+    }
+
+    /// Bearing of the leg `direction`, in degrees clockwise from north
+    pub fn bearing(&self, direction: Direction) -> f32 {
+        self.angles.get(&direction).copied().unwrap_or(direction.bearing())
+    }
+
+    /// Leg reached by `lane`: with `angles`, the one whose bearing is the closest to the
+    /// heading of its movement, less than 45 degrees away
+    fn find_exit(&self, lane: &LaneLayout) -> Option<Direction> {
+        if self.angles.is_empty() || lane.movement == Going::UTurn {
+            return Some(lane.from.destination(lane.movement));
+        }
+
+        let heading = self.bearing(lane.from) + 180.0 + lane.movement.turn();
+        let gap = |leg: Direction| ((self.bearing(leg) - heading + 180.0).rem_euclid(360.0) - 180.0).abs();
+        Direction::LEGS
+            .into_iter()
+            .filter(|&leg| leg != lane.from && self.angles.contains_key(&leg))
+            .min_by(|&a, &b| gap(a).total_cmp(&gap(b)))
+            .filter(|&leg| gap(leg) < 45.0)
+    }
+
+    /// Leg reached by `lane`, its compass destination when no leg matches
+    pub fn exit(&self, lane: &LaneLayout) -> Direction {
+        self.find_exit(lane).unwrap_or(lane.from.destination(lane.movement))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.roundabout, self.lanes.is_empty()) {
+            (Some(roundabout), true) => roundabout.validate()?,
+            (Some(_), false) => return Err("a roundabout generates its own lanes, remove `lanes`".to_string()),
+            (None, true) => return Err("no lanes".to_string()),
+            (None, false) => {}
+        }
+        if self.roundabout.is_some() && !self.angles.is_empty() {
+            return Err("a roundabout has four square legs, remove `angles`".to_string());
+        }
+        if !self.angles.is_empty() && self.angles.len() < 3 {
+            return Err(format!("a junction has at least 3 legs, not {}", self.angles.len()));
+        }
+
+        let mut lanes = HashSet::new();
+        for lane in &self.lanes {
+            if !lanes.insert((lane.from, lane.movement)) {
+                return Err(format!("two {:?} lanes on the {:?} approach", lane.movement, lane.from));
+            }
+            if !self.angles.is_empty() && !self.angles.contains_key(&lane.from) {
+                return Err(format!("the {:?} approach has no angle", lane.from));
+            }
+            if lane.points.as_ref().is_some_and(|points| points.len() < 4) {
+                return Err(format!(
+                    "the {:?} lane on the {:?} approach needs at least 4 points",
+                    lane.movement, lane.from
+                ));
+            }
+        }
+
+        let mut exits = HashMap::new();
+        for lane in &self.lanes {
+            let Some(exit) = self.find_exit(lane) else {
+                return Err(format!("no leg for the {:?} lane on the {:?} approach", lane.movement, lane.from));
+            };
+            if let Some(other) = exits.insert((lane.from, exit), lane.movement) {
+                return Err(format!(
+                    "the {:?} and {:?} lanes on the {:?} approach both lead to {:?}",
+                    other, lane.movement, lane.from, exit
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Paths built from a layout, with the conflict data of the controllers
+#[derive(Debug)]
+pub struct Geometry {
+    pub paths: Vec<Rc<Path>>,
+
+    /// Points where two paths cross
+    pub conflicts: Vec<Conflict>,
+
+    /// Pairs of lanes whose paths cross, in both orders
+    crossings: HashSet<(Lane, Lane)>,
+
+    /// Ring shared by the paths, whose cars yield to the circulating ones instead of the crossing ones
+    pub roundabout: Option<Roundabout>,
+
+    /// Bearings of the legs given by the layout, drawn instead of the square crossing
+    pub legs: Vec<f32>,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::new(&Layout::default())
+    }
+}
+
+impl Geometry {
+    pub fn new(layout: &Layout) -> Geometry {
+        let lanes = match &layout.roundabout {
+            Some(roundabout) => roundabout.lane_layouts(),
+            None => layout.lanes.clone(),
+        };
+        let paths = lanes
+            .iter()
+            .map(|lane| Rc::new(Path::new(lane, layout)))
+            .collect::<Vec<_>>();
+        let conflicts = find_conflicts(&paths);
+
+        let crossings = conflicts
+            .iter()
+            .flat_map(|conflict| {
+                let (a, b) = (conflict.paths.0.lane(), conflict.paths.1.lane());
+                [(a, b), (b, a)]
+            })
+            .collect();
+
+        Geometry {
+            paths,
+            conflicts,
+            crossings,
+            roundabout: layout.roundabout,
+            legs: layout.angles.values().copied().collect(),
+        }
+    }
+
+    /// Digest of the lanes and of their points to the centimetre, telling whether a
+    /// snapshot was taken on the same lanes
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, stable across builds unlike the hasher of the standard library
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |value: i64| {
+            for byte in value.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        for path in &self.paths {
+            write(path.coming_from as i64);
+            write(path.going_to as i64);
+            for point in path.points() {
+                write((point.x * 100.0).round() as i64);
+                write((point.y * 100.0).round() as i64);
+            }
+        }
+
+        hash
+    }
+
+    /// Lanes whose path crosses the path of `lane`
+    pub fn crossing_lanes(&self, lane: Lane) -> Vec<Lane> {
+        self.paths
+            .iter()
+            .map(|path| path.lane())
+            .filter(|&other| self.crossings.contains(&(lane, other)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(layout: &str) -> String {
+        toml::from_str::<Layout>(layout).unwrap().validate().unwrap_err()
+    }
+
+    #[test]
+    fn accepts_the_default_and_angled_layouts() {
+        Layout::default().validate().unwrap();
+        Layout::roundabout(Roundabout::default()).validate().unwrap();
+
+        let layout: Layout = toml::from_str(
+            r#"
+            lanes = [
+                { from = "North", movement = "Straight", offset = 6 },
+                { from = "North", movement = "SlightRight", offset = 10 },
+                { from = "SouthWest", movement = "SlightLeft", offset = 4 },
+            ]
+            angles = { North = 0, South = 180, SouthWest = 225 }
+            "#,
+        )
+        .unwrap();
+        layout.validate().unwrap();
+        assert_eq!(layout.exit(&layout.lanes[1]), Direction::SouthWest);
+        assert_eq!(layout.exit(&layout.lanes[2]), Direction::North);
+    }
+
+    #[test]
+    fn rejects_inconsistent_lanes() {
+        assert_eq!(error("lanes = []"), "no lanes");
+        assert_eq!(
+            error(
+                r#"lanes = [
+                    { from = "North", movement = "Left", offset = 1.5 },
+                    { from = "North", movement = "Left", offset = 4 },
+                ]"#
+            ),
+            "two Left lanes on the North approach"
+        );
+        assert_eq!(
+            error(r#"lanes = [{ from = "East", movement = "Straight", points = [[70, 28], [0, 28]] }]"#),
+            "the Straight lane on the East approach needs at least 4 points"
+        );
+        assert_eq!(
+            error(
+                r#"lanes = [{ from = "North", movement = "Straight" }]
+                roundabout = {}"#
+            ),
+            "a roundabout generates its own lanes, remove `lanes`"
+        );
+    }
+
+    #[test]
+    fn rejects_lanes_without_a_leg() {
+        let lane = r#"lanes = [{ from = "North", movement = "SlightRight", offset = 10 }]"#;
+
+        assert_eq!(
+            error(&format!("{}\nangles = {{ North = 0, South = 180 }}", lane)),
+            "a junction has at least 3 legs, not 2"
+        );
+        assert_eq!(
+            error(&format!("{}\nangles = {{ East = 90, South = 180, West = 270 }}", lane)),
+            "the North approach has no angle"
+        );
+        assert_eq!(
+            error(&format!("{}\nangles = {{ North = 0, East = 90, South = 180 }}", lane)),
+            "no leg for the SlightRight lane on the North approach"
+        );
+        assert_eq!(
+            error(
+                r#"lanes = [
+                    { from = "North", movement = "SlightRight", offset = 10 },
+                    { from = "North", movement = "Right", offset = 12 },
+                ]
+                angles = { North = 0, South = 180, West = 250 }"#
+            ),
+            "the SlightRight and Right lanes on the North approach both lead to West"
+        );
+    }
+
+    #[test]
+    fn finds_the_crossings_of_the_default_layout() {
+        let geometry = Geometry::default();
+        let path = |lane: Lane| geometry.paths.iter().find(|path| path.lane() == lane).unwrap().clone();
+        let (north, east) = (path((Direction::North, Going::Straight)), path((Direction::East, Going::Straight)));
+
+        let conflicts = find_conflicts(&[north.clone(), east.clone()]);
+        assert_eq!(conflicts.len(), 1);
+        assert!((conflicts[0].point.x - north.points()[0].x).abs() < 1e-3);
+        assert!((conflicts[0].point.y - east.points()[0].y).abs() < 1e-3);
+
+        let crossing = geometry.crossing_lanes((Direction::North, Going::Straight));
+        assert!(crossing.contains(&(Direction::East, Going::Straight)));
+        assert!(crossing.contains(&(Direction::West, Going::Straight)));
+        assert!(!crossing.contains(&(Direction::South, Going::Straight)));
+        assert!(!crossing.contains(&(Direction::North, Going::Straight)));
+        assert!(geometry.crossing_lanes((Direction::North, Going::Right)).is_empty());
+
+        for lane in geometry.paths.iter().map(|path| path.lane()) {
+            for other in geometry.crossing_lanes(lane) {
+                assert!(geometry.crossing_lanes(other).contains(&lane));
+            }
+        }
+    }
+}
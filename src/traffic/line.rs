@@ -1,71 +1,128 @@
-use crate::constant::{ CAR_LENGTH, CAR_SAFE_DISTANCE };
-use crate::traffic::{ Car, Direction, Going, Path, TrafficState };
+use crate::constant::{CAR_LENGTH, CAR_WIDTH};
+use crate::traffic::{ Car, CarStatus, Geometry, Going, Params, Path, TrafficState };
+use macroquad::math::Vec2;
 use std::rc::Rc;
 
+/// Lanes of an approach, `path_cars[i]` being the cars on `paths[i]`
 #[derive(Debug, Clone)]
 pub struct Line {
-    pub paths: [Rc<Path>; 3],
-    pub path_cars: [Vec<Car>; 3],
+    pub paths: Vec<Rc<Path>>,
+    pub path_cars: Vec<Vec<Car>>,
 }
 
 impl Line {
-    pub fn new(coming_from: Direction) -> Self {
+    pub fn new(paths: Vec<Rc<Path>>) -> Self {
         Line {
-            paths: [
-                Rc::new(Path::new(coming_from, Going::Straight)),
-                Rc::new(Path::new(coming_from, Going::Left)),
-                Rc::new(Path::new(coming_from, Going::Right)),
-            ],
-
-            path_cars: [vec![], vec![], vec![]],
+            path_cars: vec![vec![]; paths.len()],
+            paths,
         }
     }
-    pub fn path_cars(&self, path: &Path) -> &Vec<Car> {
-        &self.path_cars[path.going_to as usize]
+
+    /// Cars of the lane of this approach with the movement `going_to`
+    pub fn cars_going(&self, going_to: Going) -> &[Car] {
+        match self.paths.iter().position(|path| path.going_to == going_to) {
+            Some(index) => &self.path_cars[index],
+            None => &[],
+        }
     }
 
-    pub fn path_cars_mut(&mut self, path: &Path) -> &mut Vec<Car> {
-        &mut self.path_cars[path.going_to as usize]
+    pub fn path_cars(&self, path: &Path) -> &[Car] {
+        self.cars_going(path.going_to)
     }
 
     pub fn update(&mut self, traffic_state: &TrafficState) {
         self.remove_cars();
 
-        for path in self.paths.iter() {
-            let cars = &mut self.path_cars[path.going_to as usize];
+        let line = &traffic_state.lines[self.paths.first().map_or(0, |path| path.coming_from as usize)];
+
+        for cars in self.path_cars.iter_mut() {
 
             let mut prev_car: Option<&Car> = None;
 
             for car in cars.iter_mut() {
-                car.update(prev_car, traffic_state);
+                let leader = line.leader(car, prev_car, &traffic_state.lines, &traffic_state.geometry);
+                car.update(leader, traffic_state);
 
                 prev_car = Some(car);
             }
         }
     }
 
-    pub fn get_free_paths(&self) -> Vec<Rc<Path>> {
+    /// Car followed by `car` of this approach: `prev_car` ahead on its path or a nearer car
+    /// of a lane it shares, and on a ring the circulating car ahead once it entered
+    pub fn leader<'a>(&'a self, car: &Car, prev_car: Option<&'a Car>, lines: &'a [Line], geometry: &Geometry) -> Option<&'a Car> {
+        if let Some(roundabout) = &geometry.roundabout {
+            if car.get_status() != CarStatus::BeforeTurn {
+                return roundabout.leader(car, lines);
+            }
+        }
+
+        match (prev_car, self.shared_lane_leader(car)) {
+            (Some(prev_car), Some(other)) if other.border_distance() < prev_car.border_distance() => Some(other),
+            (None, other) => other,
+            (prev_car, _) => prev_car,
+        }
+    }
+
+    /// Nearest car ahead on the approach among the other paths starting at the same point,
+    /// which share their lane until the intersection, or just past its stop line, and among
+    /// the cars of the other lanes in front of it while one of them moves over
+    pub fn shared_lane_leader(&self, car: &Car) -> Option<&Car> {
+        if car.get_status() != CarStatus::BeforeTurn {
+            return None;
+        }
+
+        let heading = Vec2::from_angle(car.rotation);
+        let in_front = |other: &Car| {
+            (car.is_moving_over() || other.is_moving_over()) && (other.pos - car.pos).perp_dot(heading).abs() < CAR_WIDTH
+        };
+
         self.paths
             .iter()
-            .filter(|path| {
-                let cars = self.path_cars(path);
+            .zip(&self.path_cars)
+            .filter(|(path, _)| path.going_to != car.path.going_to)
+            .flat_map(|(path, cars)| cars.iter().map(move |other| (path, other)))
+            .filter(|(path, other)| path.point(0) == car.path.point(0) || in_front(other))
+            .map(|(_, other)| other)
+            .filter(|other| other.point_index() <= 1 && other.border_distance() > car.border_distance())
+            .min_by(|a, b| a.border_distance().total_cmp(&b.border_distance()))
+    }
 
-                if let Some(car) = cars.last() {
-                    if car.border_distance() < CAR_LENGTH + CAR_SAFE_DISTANCE {
-                        return false;
-                    }
-                }
+    pub fn get_free_paths(&self, params: &Params) -> Vec<Rc<Path>> {
+        self.paths
+            .iter()
+            .filter(|path| {
+                // lanes starting at the same point, as imported lanes allowing several movements
+                let same_start = self.paths.iter().zip(&self.path_cars).filter(|(other, _)| other.point(0) == path.point(0));
 
-                true
+                same_start.filter_map(|(_, cars)| cars.last()).all(|car| {
+                    car.border_distance() >= CAR_LENGTH + params.safe_distance
+                }) && self.path_cars.iter().flatten().filter(|car| car.is_moving_over()).all(|car| {
+                    // a car moving over from or to the lane
+                    let (Some(start), Some(stop)) = (path.point(0), path.point(1)) else {
+                        return true;
+                    };
+                    let (offset, heading) = (car.pos - start, (stop - start).normalize());
+                    offset.perp_dot(heading).abs() >= CAR_WIDTH || offset.dot(heading) >= CAR_LENGTH + params.safe_distance
+                })
             })
             .cloned()
             .collect()
     }
 
-    pub fn gen_car(&mut self, path: Rc<Path>) {
-        let car = Car::new(path.clone());
+    pub fn gen_car(&mut self, car: Car) {
+        if let Some(index) = self.paths.iter().position(|path| path.going_to == car.path.going_to) {
+            self.path_cars[index].push(car);
+        }
+    }
 
-        self.path_cars_mut(&path).push(car);
+    /// Number of cars waiting before the intersection
+    pub fn queue_length(&self, params: &Params) -> usize {
+        self.path_cars
+            .iter()
+            .flatten()
+            .filter(|car| car.get_status() == CarStatus::BeforeTurn && car.velocity < params.min_speed)
+            .count()
     }
 
     pub fn remove_cars(&mut self) {
@@ -1,14 +1,53 @@
 mod car;
+mod conflict;
 mod curve;
+mod external_control;
+mod lane_change;
+mod layout;
 mod line;
+mod network;
+mod network_file;
+mod params;
 mod path;
+mod perception;
+mod roundabout;
+mod routing;
 mod path_collisions;
+mod snapshot;
 mod state;
+mod sumo;
+mod v2i;
 
-pub use car::{Car, Direction, Going};
+pub use car::{Car, CarSnapshot, CarStatus, Direction, Going};
+
+pub use conflict::{find_conflicts, Conflict};
+
+pub use external_control::ExternalControl;
 
 pub use state::TrafficState;
 
+pub use lane_change::LaneChangeConfig;
+
+pub use layout::{Geometry, LaneLayout, Layout};
+
 pub use line::Line;
 
-pub use path::Path;
+pub use network::Network;
+
+pub use network_file::NetworkFile;
+
+pub use params::Params;
+
+pub use path::{border_point, outward, Path};
+
+pub use perception::{Perception, SensorConfig};
+
+pub use roundabout::Roundabout;
+
+pub use routing::{Routing, RoutingConfig, Trip, TripDemand};
+
+pub use snapshot::Snapshot;
+
+pub use sumo::import_sumo;
+
+pub use v2i::{ChannelConfig, MessageCounts, V2i};
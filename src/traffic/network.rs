@@ -0,0 +1,279 @@
+use crate::constant::{TICK, WORLD_SIZE};
+use crate::traffic::{Direction, Routing, Trip, TripDemand, TrafficState};
+use macroquad::math::Vec2;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+
+/// Intersections laid out on a grid, each one a `TrafficState` of `WORLD_SIZE` metres running
+/// its own controller, the cars leaving an intersection entering the linked one
+#[derive(Debug, Clone)]
+pub struct Network {
+    /// Size of the grid holding every intersection
+    pub columns: usize,
+    pub rows: usize,
+
+    pub intersections: Vec<TrafficState>,
+
+    /// Cell of each intersection, as `(column, row)`
+    pub cells: Vec<(usize, usize)>,
+
+    /// Intersection linked to each side of each intersection, by leg, the intercardinal
+    /// legs leading out of the network
+    neighbours: Vec<[Option<usize>; 8]>,
+
+    /// Cars waiting at the border of each intersection, by approach, for a free lane,
+    /// with their trip if they have one
+    pending: Vec<[VecDeque<Option<Trip>>; 8]>,
+
+    /// Trips of the cars inside each intersection, by car id
+    trips: Vec<HashMap<usize, Trip>>,
+
+    /// Origin-destination demand, the cars without trip turning at random
+    pub demand: Vec<TripDemand>,
+    pub routing: Routing,
+
+    /// Cars which went from an intersection to the next one
+    pub transfers: usize,
+
+    /// Cars which left the network
+    pub exits: usize,
+
+    /// Time from entering the network to leaving it at the destination, by completed trip
+    pub trip_times: Vec<f64>,
+}
+
+impl Network {
+    /// Intersections placed on `cells`, `links` joining two of them by their index; the
+    /// links between cells which are not side by side are ignored
+    pub fn new(cells: Vec<(usize, usize)>, intersections: Vec<TrafficState>, links: &[(usize, usize)]) -> Network {
+        let mut neighbours = vec![[None; 8]; cells.len()];
+        for &(a, b) in links {
+            if let Some(side) = side_towards(cells[a], cells[b]) {
+                neighbours[a][side as usize] = Some(b);
+                neighbours[b][side.opposite() as usize] = Some(a);
+            }
+        }
+
+        Network {
+            columns: cells.iter().map(|&(column, _)| column + 1).max().unwrap_or(0),
+            rows: cells.iter().map(|&(_, row)| row + 1).max().unwrap_or(0),
+            pending: vec![Default::default(); cells.len()],
+            trips: vec![HashMap::new(); cells.len()],
+            demand: Vec::new(),
+            routing: Routing::default(),
+            intersections,
+            cells,
+            neighbours,
+            transfers: 0,
+            exits: 0,
+            trip_times: Vec::new(),
+        }
+    }
+
+    /// Grid whose intersection of row-major `index` is `intersection(index)`, every
+    /// intersection linked to the ones beside it
+    pub fn grid(columns: usize, rows: usize, intersection: impl FnMut(usize) -> TrafficState) -> Network {
+        let count = columns * rows;
+        let cells = (0..count).map(|index| (index % columns, index / columns)).collect();
+        let links = (0..count)
+            .flat_map(|index| {
+                let east = (index % columns + 1 < columns).then_some((index, index + 1));
+                let south = (index + columns < count).then_some((index, index + columns));
+                east.into_iter().chain(south)
+            })
+            .collect::<Vec<_>>();
+
+        Network::new(cells, (0..count).map(intersection).collect(), &links)
+    }
+
+    /// Top-left corner of an intersection, in metres
+    pub fn offset(&self, index: usize) -> Vec2 {
+        let (column, row) = self.cells[index];
+        Vec2::new(column as f32, row as f32) * WORLD_SIZE
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.columns as f32, self.rows as f32) * WORLD_SIZE
+    }
+
+    /// Intersection linked to the `side` border of the intersection `index`
+    pub fn neighbour(&self, index: usize, side: Direction) -> Option<usize> {
+        self.neighbours[index][side as usize]
+    }
+
+    /// Intersection entered by the cars leaving `index` by `side`, a neighbour without
+    /// lane on that approach only letting them out of the network
+    pub fn entry(&self, index: usize, side: Direction) -> Option<usize> {
+        self.neighbour(index, side)
+            .filter(|&neighbour| !self.intersections[neighbour].lines[side.opposite() as usize].paths.is_empty())
+    }
+
+    /// Approaches of an intersection fed from outside the network
+    pub fn boundary_approaches(&self, index: usize) -> Vec<Direction> {
+        Direction::LEGS
+            .into_iter()
+            .filter(|&side| self.neighbour(index, side).is_none())
+            .collect()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.intersections.iter_mut().for_each(TrafficState::toggle_pause);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.intersections.iter().any(|intersection| intersection.statistics.is_open)
+    }
+
+    pub fn update(&mut self) {
+        self.spawn_trips();
+
+        for index in 0..self.intersections.len() {
+            // the cars at the end of their path are removed by this update
+            let intersection = &self.intersections[index];
+            let leaving = intersection
+                .cars()
+                .filter(|car| car.is_done())
+                .map(|car| (car.id, car.path.lane(), car.path.exit, intersection.time - car.start_time))
+                .collect::<Vec<_>>();
+
+            self.intersections[index].update();
+
+            for (id, lane, side, crossing_time) in leaving {
+                self.routing.record(index, lane, crossing_time);
+
+                let trip = self.trips[index].remove(&id);
+
+                match self.entry(index, side) {
+                    Some(neighbour) => {
+                        self.pending[neighbour][side.opposite() as usize].push_back(trip);
+                        self.transfers += 1;
+                    }
+                    None => {
+                        if let Some(trip) = trip.filter(|trip| trip.destination == index) {
+                            self.trip_times.push(self.intersections[index].time - trip.start_time);
+                        }
+                        self.exits += 1;
+                    }
+                }
+            }
+        }
+
+        for index in 0..self.intersections.len() {
+            for approach in Direction::LEGS {
+                self.enter(index, approach);
+            }
+        }
+    }
+
+    /// Cars of the origin-destination demand arriving during the next tick, each one
+    /// queued on the border approach of its origin with the fastest route
+    fn spawn_trips(&mut self) {
+        for demand in self.demand.clone() {
+            let rng = &mut self.intersections[demand.origin].rng;
+            if !rng.gen_bool((demand.rate * TICK / 3600.0).clamp(0.0, 1.0)) {
+                continue;
+            }
+
+            let best = Direction::LEGS
+                .into_iter()
+                .filter(|&approach| self.neighbour(demand.origin, approach).is_none())
+                .filter_map(|approach| Some((approach, self.routing.route(self, demand.origin, approach, demand.destination)?)))
+                .min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b));
+
+            if let Some((approach, (route, _))) = best {
+                let trip = Trip {
+                    destination: demand.destination,
+                    route,
+                    start_time: self.intersections[demand.origin].time,
+                };
+                self.pending[demand.origin][approach as usize].push_back(Some(trip));
+            }
+        }
+    }
+
+    /// Lets the first car waiting on an approach in once its lane is free, on the movement
+    /// leading to the next side of its route
+    fn enter(&mut self, index: usize, approach: Direction) {
+        let Some(trip) = self.pending[index][approach as usize].front().cloned() else {
+            return;
+        };
+
+        // the planned route is kept when no other one is found
+        let trip = trip.map(|mut trip| {
+            if self.routing.config.reroute {
+                if let Some((route, _)) = self.routing.route(self, index, approach, trip.destination) {
+                    trip.route = route;
+                }
+            }
+            trip
+        });
+        let intersection = &mut self.intersections[index];
+        let going_to = trip.as_ref().and_then(|trip| {
+            let side = *trip.route.front()?;
+            intersection.lines[approach as usize]
+                .paths
+                .iter()
+                .find(|path| path.exit == side)
+                .map(|path| path.going_to)
+        });
+
+        let free_paths = intersection.lines[approach as usize].get_free_paths(&intersection.params);
+        let id = intersection.next_car_id;
+
+        match going_to {
+            Some(going_to) => match free_paths.iter().find(|path| path.going_to == going_to) {
+                Some(path) => intersection.gen_car_on_path(path),
+                None => return,
+            },
+            None if free_paths.is_empty() => return,
+            None => intersection.gen_car(approach),
+        }
+
+        self.pending[index][approach as usize].pop_front();
+        if let (Some(mut trip), Some(_)) = (trip, going_to) {
+            trip.route.pop_front();
+            self.trips[index].insert(id, trip);
+        }
+    }
+}
+
+/// Side of the cell `from` touching the cell `to`, if they are side by side
+fn side_towards(from: (usize, usize), to: (usize, usize)) -> Option<Direction> {
+    let (column, row) = from;
+
+    Direction::ALL.into_iter().find(|side| {
+        let next = match side {
+            Direction::North => row.checked_sub(1).map(|row| (column, row)),
+            Direction::East => Some((column + 1, row)),
+            Direction::South => Some((column, row + 1)),
+            Direction::West => column.checked_sub(1).map(|column| (column, row)),
+            _ => None,
+        };
+        next == Some(to)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_planned_route_when_rerouting_finds_none() {
+        let mut network = Network::grid(2, 1, |index| TrafficState::with_geometry(index as u64, Default::default()));
+        network.routing.config.reroute = true;
+
+        // without U-turns, no route leads from the west approach of the east node back west
+        assert!(network.routing.route(&network, 1, Direction::West, 0).is_none());
+        let trip = Trip {
+            destination: 0,
+            route: VecDeque::from([Direction::East]),
+            start_time: 0.0,
+        };
+        network.pending[1][Direction::West as usize].push_back(Some(trip));
+        network.enter(1, Direction::West);
+
+        let car = network.intersections[1].cars().next().unwrap();
+        assert_eq!(car.path.exit, Direction::East);
+        assert_eq!(network.trips[1][&car.id].destination, 0);
+    }
+}
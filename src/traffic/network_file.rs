@@ -0,0 +1,206 @@
+use crate::traffic::{import_sumo, Geometry, Layout, Network, Routing, RoutingConfig, TrafficState, TripDemand};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Road network read from a TOML file, each node an intersection on a grid cell:
+///
+/// ```toml
+/// [layouts.t_junction]    # lanes of the intersections, see `Layout`
+/// lanes = [
+///     { from = "West", movement = "Straight", offset = 6.75 },
+///     { from = "West", movement = "Right", offset = 11.4, speed_limit = 6 },
+///     { from = "East", movement = "Straight", offset = 6.75 },
+///     { from = "East", movement = "Left", offset = 2.1 },
+///     { from = "South", movement = "Left", offset = 2.1 },
+///     { from = "South", movement = "Right", offset = 11.4 },
+/// ]
+///
+/// [[nodes]]
+/// name = "station"
+/// column = 0
+/// row = 0
+/// layout = "t_junction"   # four approaches with three lanes each when omitted
+///
+/// [[nodes]]
+/// name = "market"
+/// column = 1
+/// row = 0
+///
+/// [[links]]               # road between two nodes on cells side by side
+/// from = "station"
+/// to = "market"
+///
+/// [[trips]]               # optional, vehicles per hour entering at a node to leave at another one
+/// from = "station"
+/// to = "market"
+/// rate = 120
+///
+/// [routing]
+/// reroute = true          # plan the route again at each node with the current crossing times
+/// ```
+///
+/// The nodes may also be junctions of a SUMO network, with `sumo = "area.net.xml"` at the
+/// top of the file, relative to it, and `junction = "J3"` instead of `layout`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkFile {
+    #[serde(default)]
+    pub layouts: HashMap<String, Layout>,
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    #[serde(default)]
+    pub trips: Vec<TripFile>,
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    pub sumo: Option<PathBuf>,
+
+    /// Layouts of the SUMO junctions used by the nodes, filled by `load`
+    #[serde(skip)]
+    junctions: HashMap<String, Layout>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Node {
+    pub name: String,
+    pub column: usize,
+    pub row: usize,
+    pub layout: Option<String>,
+    pub junction: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TripFile {
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+}
+
+impl NetworkFile {
+    pub fn load(path: &Path) -> Result<NetworkFile, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+
+        let mut network: NetworkFile =
+            toml::from_str(&content).map_err(|error| format!("invalid network {}: {}", path.display(), error))?;
+        network
+            .validate()
+            .map_err(|error| format!("invalid network {}: {}", path.display(), error))?;
+
+        if let Some(sumo) = &network.sumo {
+            let sumo = path.parent().unwrap_or(Path::new("")).join(sumo);
+            for junction in network.nodes.iter().filter_map(|node| node.junction.as_ref()) {
+                network.junctions.insert(junction.clone(), import_sumo(&sumo, Some(junction))?);
+            }
+        }
+
+        Ok(network)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, layout) in &self.layouts {
+            layout.validate().map_err(|error| format!("layout `{}`: {}", name, error))?;
+        }
+
+        let mut cells = HashMap::new();
+        for node in &self.nodes {
+            if let Some(layout) = &node.layout {
+                if !self.layouts.contains_key(layout) {
+                    return Err(format!("node `{}` uses the unknown layout `{}`", node.name, layout));
+                }
+            }
+            if node.junction.is_some() && (node.layout.is_some() || self.sumo.is_none()) {
+                return Err(format!("node `{}` needs a `sumo` file and no `layout` to use a junction", node.name));
+            }
+            if let Some(other) = cells.insert((node.column, node.row), &node.name) {
+                return Err(format!("nodes `{}` and `{}` share a cell", other, node.name));
+            }
+        }
+
+        for link in &self.links {
+            let (from, to) = (self.node(&link.from)?, self.node(&link.to)?);
+            if from.column.abs_diff(to.column) + from.row.abs_diff(to.row) != 1 {
+                return Err(format!("nodes `{}` and `{}` are not side by side", from.name, to.name));
+            }
+        }
+
+        for trip in &self.trips {
+            self.node(&trip.from)?;
+            self.node(&trip.to)?;
+        }
+
+        Ok(())
+    }
+
+    fn node(&self, name: &str) -> Result<&Node, String> {
+        self.nodes
+            .iter()
+            .find(|node| node.name == name)
+            .ok_or_else(|| format!("unknown node `{}`", name))
+    }
+
+    fn index(&self, name: &str) -> Result<usize, String> {
+        self.nodes
+            .iter()
+            .position(|node| node.name == name)
+            .ok_or_else(|| format!("unknown node `{}`", name))
+    }
+
+    /// Network whose node of `index` is `intersection(index, geometry)`, the nodes
+    /// sharing a layout sharing its geometry
+    pub fn build(&self, mut intersection: impl FnMut(usize, Rc<Geometry>) -> TrafficState) -> Result<Network, String> {
+        let default_geometry = Rc::new(Geometry::default());
+        let geometries = self
+            .layouts
+            .iter()
+            .map(|(name, layout)| (name.as_str(), Rc::new(Geometry::new(layout))))
+            .collect::<HashMap<_, _>>();
+
+        let mut intersections = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let geometry = match (&node.layout, &node.junction) {
+                (Some(layout), _) => geometries
+                    .get(layout.as_str())
+                    .ok_or_else(|| format!("unknown layout `{}`", layout))?
+                    .clone(),
+                (None, Some(junction)) => Rc::new(Geometry::new(
+                    self.junctions
+                        .get(junction)
+                        .ok_or_else(|| format!("junction `{}` was not imported", junction))?,
+                )),
+                (None, None) => default_geometry.clone(),
+            };
+            intersections.push(intersection(index, geometry));
+        }
+
+        let links = self
+            .links
+            .iter()
+            .map(|link| Ok((self.index(&link.from)?, self.index(&link.to)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let cells = self.nodes.iter().map(|node| (node.column, node.row)).collect();
+
+        let mut network = Network::new(cells, intersections, &links);
+        network.routing = Routing::new(self.routing);
+        network.demand = self
+            .trips
+            .iter()
+            .map(|trip| {
+                Ok(TripDemand {
+                    origin: self.index(&trip.from)?,
+                    destination: self.index(&trip.to)?,
+                    rate: trip.rate,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(network)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::constant::{
+    CAR_ACCELERATION, CAR_DECELERATION, CAR_SAFE_DISTANCE, MAX_LATERAL_ACCELERATION, MAX_SPEED, MIN_SPEED,
+};
+use serde::{Deserialize, Serialize};
+
+/// Driving parameters shared by every car, defaulting to the values of `constant`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Params {
+    pub safe_distance: f32,
+    pub max_speed: f32,
+    pub min_speed: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+
+    /// Largest lateral acceleration in the curves, in m/s², capping the speed on the turns
+    pub lateral_acceleration: f32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            safe_distance: CAR_SAFE_DISTANCE,
+            max_speed: MAX_SPEED,
+            min_speed: MIN_SPEED,
+            acceleration: CAR_ACCELERATION,
+            deceleration: CAR_DECELERATION,
+            lateral_acceleration: MAX_LATERAL_ACCELERATION,
+        }
+    }
+}
+
+impl Params {
+    /// Sets a parameter from its name, as used in experiment files
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        match name {
+            "safe_distance" => self.safe_distance = value,
+            "max_speed" => self.max_speed = value,
+            "min_speed" => self.min_speed = value,
+            "acceleration" => self.acceleration = value,
+            "deceleration" => self.deceleration = value,
+            "lateral_acceleration" => self.lateral_acceleration = value,
+            _ => return Err(format!("unknown parameter `{}`", name)),
+        }
+
+        Ok(())
+    }
+}
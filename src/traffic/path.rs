@@ -1,5 +1,6 @@
-use crate::constant::{CAR_PADDING, ROAD_WIDTH, STRAIGHT_LENGTH, WINDOW_SIZE};
+use crate::constant::WORLD_SIZE;
 use crate::traffic::curve::quadratic_curve;
+use crate::traffic::layout::{Lane, LaneLayout, Layout};
 use crate::traffic::{Direction, Going};
 use macroquad::math::Vec2;
 use std::ops::{Mul, Sub};
@@ -9,171 +10,156 @@ pub struct Path {
     pub coming_from: Direction,
     pub going_to: Going,
 
-    points: Vec<Vec2>,
-}
+    /// Leg by which the path leaves the junction
+    pub exit: Direction,
 
+    /// Highest speed allowed on the lane, in m/s
+    pub speed_limit: Option<f32>,
 
-/// Returns the point on the border where the car should appear or disappear
-fn border_point(coming_from: Direction, going_to: Going) -> Vec2 {
-    let lane = (coming_from, going_to);
+    points: Vec<Vec2>,
 
-    match lane {
-        (Direction::North, Going::Right) => Vec2::new(
-            WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 2.0 + CAR_PADDING,
-            0.0,
-        ),
-        (Direction::North, Going::Straight) => {
-            Vec2::new(WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 4.0, 0.0)
-        }
-        (Direction::North, Going::Left) => Vec2::new(WINDOW_SIZE as f32 / 2.0 - CAR_PADDING, 0.0),
-
-        (Direction::East, Going::Right) => Vec2::new(
-            WINDOW_SIZE as f32,
-            WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 2.0 + CAR_PADDING,
-        ),
-        (Direction::East, Going::Straight) => Vec2::new(
-            WINDOW_SIZE as f32,
-            WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 4.0,
-        ),
-        (Direction::East, Going::Left) => {
-            Vec2::new(WINDOW_SIZE as f32, WINDOW_SIZE as f32 / 2.0 - CAR_PADDING)
-        }
+    /// Curvature of the polyline at each point, in 1/m
+    curvatures: Vec<f32>,
+}
 
-        (Direction::South, Going::Left) => {
-            Vec2::new(WINDOW_SIZE as f32 / 2.0 + CAR_PADDING, WINDOW_SIZE as f32)
-        }
-        (Direction::South, Going::Straight) => Vec2::new(
-            WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 4.0,
-            WINDOW_SIZE as f32,
-        ),
-        (Direction::South, Going::Right) => Vec2::new(
-            WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 2.0 - CAR_PADDING,
-            WINDOW_SIZE as f32,
-        ),
-
-        (Direction::West, Going::Left) => Vec2::new(0.0, WINDOW_SIZE as f32 / 2.0 + CAR_PADDING),
-        (Direction::West, Going::Straight) => {
-            Vec2::new(0.0, WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 4.0)
-        }
-        (Direction::West, Going::Right) => Vec2::new(
-            0.0,
-            WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 2.0 - CAR_PADDING,
-        ),
-    }
+/// Unit vector from the centre of the map towards `bearing`, in degrees clockwise from north,
+/// exact on the axes so that square crossings keep their straight lanes
+pub fn outward(bearing: f32) -> Vec2 {
+    let exact = |coordinate: f32| if coordinate.abs() < 1e-6 { 0.0 } else { coordinate };
+    let (sin, cos) = bearing.to_radians().sin_cos();
+    Vec2::new(exact(sin), exact(-cos))
 }
 
-fn border_end_point(coming_from: Direction, going_to: Going) -> Vec2 {
-    let car_padding = CAR_PADDING;
+/// Point where the axis of the road at `bearing` leaves the map
+pub fn border_point(bearing: f32) -> Vec2 {
+    let heading = outward(bearing);
+    Vec2::splat(WORLD_SIZE / 2.0) + heading * (WORLD_SIZE / 2.0 / heading.abs().max_element())
+}
 
-    let lane = (coming_from, going_to);
+/// Middle of the border of the approach `direction`, at its compass bearing
+pub fn border_middle(direction: Direction) -> Vec2 {
+    border_point(direction.bearing())
+}
 
-    match lane {
-        (Direction::North, Going::Right) => Vec2::new(
-            WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 2.0 - car_padding,
-            0.0,
-        ),
-        (Direction::North, Going::Straight) => {
-            Vec2::new(WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 4.0, 0.0)
-        }
-        (Direction::North, Going::Left) => Vec2::new(WINDOW_SIZE as f32 / 2.0 + car_padding, 0.0),
-
-        (Direction::East, Going::Right) => Vec2::new(
-            WINDOW_SIZE as f32,
-            WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 2.0 - car_padding,
-        ),
-        (Direction::East, Going::Straight) => Vec2::new(
-            WINDOW_SIZE as f32,
-            WINDOW_SIZE as f32 / 2.0 + ROAD_WIDTH / 4.0,
-        ),
-        (Direction::East, Going::Left) => {
-            Vec2::new(WINDOW_SIZE as f32, WINDOW_SIZE as f32 / 2.0 + car_padding)
-        }
+/// Heading of the cars entering from the approach `direction`, at its compass bearing
+pub fn inward(direction: Direction) -> Vec2 {
+    -outward(direction.bearing())
+}
 
-        (Direction::South, Going::Left) => {
-            Vec2::new(WINDOW_SIZE as f32 / 2.0 - car_padding, WINDOW_SIZE as f32)
-        }
-        (Direction::South, Going::Straight) => Vec2::new(
-            WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 4.0,
-            WINDOW_SIZE as f32,
-        ),
-        (Direction::South, Going::Right) => Vec2::new(
-            WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 2.0 + car_padding,
-            WINDOW_SIZE as f32,
-        ),
-
-        (Direction::West, Going::Left) => Vec2::new(0.0, WINDOW_SIZE as f32 / 2.0 - car_padding),
-        (Direction::West, Going::Straight) => {
-            Vec2::new(0.0, WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 4.0)
-        }
-        (Direction::West, Going::Right) => Vec2::new(
-            0.0,
-            WINDOW_SIZE as f32 / 2.0 - ROAD_WIDTH / 2.0 + car_padding,
-        ),
+/// Point where the lane reaching `entry` along `heading_in` would meet the lane leaving
+/// `exit` along `heading_out`, if both would have to go forward
+fn corner(entry: Vec2, heading_in: Vec2, exit: Vec2, heading_out: Vec2) -> Option<Vec2> {
+    let denominator = heading_in.perp_dot(heading_out);
+    if denominator.abs() < 1e-3 {
+        return None;
     }
+
+    let along_in = (exit - entry).perp_dot(heading_out) / denominator;
+    let along_out = heading_in.perp_dot(exit - entry) / denominator;
+    (along_in > 0.0 && along_out > 0.0).then_some(entry + heading_in * along_in)
 }
 
-/// Returns the point in center associated with the border point
-fn straight_point(direction: Direction, border_point: Vec2) -> Vec2 {
-    match direction {
-        Direction::North => Vec2::new(border_point.x, border_point.y + STRAIGHT_LENGTH),
-        Direction::East => Vec2::new(border_point.x - STRAIGHT_LENGTH, border_point.y),
-        Direction::South => Vec2::new(border_point.x, border_point.y - STRAIGHT_LENGTH),
-        Direction::West => Vec2::new(border_point.x + STRAIGHT_LENGTH, border_point.y),
+/// Inverse of the radius of the circle through three points, 0 when they are aligned
+fn menger_curvature(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    let sides = a.distance(b) * b.distance(c) * c.distance(a);
+    if sides < 1e-6 {
+        return 0.0;
     }
+
+    2.0 * (b - a).perp_dot(c - a).abs() / sides
+}
+
+/// Right-hand side of a heading, the y axis pointing down
+pub fn right_of(heading: Vec2) -> Vec2 {
+    Vec2::new(-heading.y, heading.x)
 }
 
 impl Path {
-    pub fn new(coming_from: Direction, going_to: Going) -> Self {
-        let destination = coming_from.destination(going_to);
-
-        let start_point = border_point(coming_from, going_to);
-        let end_point = border_end_point(destination, going_to);
-
-        match going_to {
-            Going::Straight => Self {
-                coming_from,
-                going_to,
-
-                points: vec![
-                    start_point,
-                    straight_point(coming_from, start_point),
-                    straight_point(destination, end_point),
-                    end_point,
-                ],
-            },
-            Going::Left | Going::Right => {
-                let curve_start_point = straight_point(coming_from, start_point);
-                let curve_end_point = straight_point(destination, end_point);
-
-                let center = Vec2::new(WINDOW_SIZE as f32 / 2.0, WINDOW_SIZE as f32 / 2.0);
+    pub fn new(lane: &LaneLayout, layout: &Layout) -> Self {
+        let coming_from = lane.from;
+        let going_to = lane.movement;
+        let exit = layout.exit(lane);
+
+        if let Some(points) = &lane.points {
+            return Self::with_points(
+                (coming_from, going_to),
+                exit,
+                lane.speed_limit,
+                points.iter().map(|&point| Vec2::from(point)).collect(),
+            );
+        }
+
+        let (entry_bearing, exit_bearing) = (layout.bearing(coming_from), layout.bearing(exit));
+
+        let heading_in = -outward(entry_bearing);
+        let heading_out = outward(exit_bearing);
+
+        let start_point = border_point(entry_bearing) + right_of(heading_in) * lane.offset;
+        let end_point = border_point(exit_bearing) + right_of(heading_out) * lane.exit_offset.unwrap_or(lane.offset);
 
+        // the turns start at the same distance from the centre on every leg, wherever it meets the border
+        let turn_distance = WORLD_SIZE / 2.0 - layout.approach_length;
+        let center = Vec2::new(WORLD_SIZE / 2.0, WORLD_SIZE / 2.0);
+        let curve_start_point = center - heading_in * turn_distance + right_of(heading_in) * lane.offset;
+        let curve_end_point =
+            center + heading_out * turn_distance + right_of(heading_out) * lane.exit_offset.unwrap_or(lane.offset);
+
+        let points = match going_to {
+            Going::Straight => vec![start_point, curve_start_point, curve_end_point, end_point],
+            _ => {
                 // vector between curve_start_point and curve_end_point
                 let line = curve_start_point.sub(curve_end_point);
 
                 // perpendicular vector from center to line
                 let radial_vector = Vec2::new(-line.y, line.x);
-                let control_point = match going_to {
-                    Going::Left => center.sub(radial_vector.mul(0.1)),
-                    Going::Right => center.sub(radial_vector.mul(1.0)),
-                    _ => unreachable!(),
+                let curvature = lane.curvature.unwrap_or(match going_to {
+                    Going::Right | Going::UTurn => 1.0,
+                    _ => 0.1,
+                });
+
+                // on other junctions than the square crossing, the turn follows the corner
+                // between the approach and the exit lanes
+                let corner = match (lane.curvature, layout.angles.is_empty()) {
+                    (None, false) => corner(curve_start_point, heading_in, curve_end_point, heading_out),
+                    _ => None,
                 };
+                let control_point = corner.unwrap_or_else(|| center.sub(radial_vector.mul(curvature)));
 
                 let curve = quadratic_curve(curve_start_point, control_point, curve_end_point);
 
-                Self {
-                    coming_from,
-                    going_to,
-
-                    points: [start_point, curve_start_point]
-                        .into_iter()
-                        .chain(curve)
-                        .chain([curve_end_point, end_point])
-                        .collect(),
-                }
+                [start_point, curve_start_point]
+                    .into_iter()
+                    .chain(curve)
+                    .chain([curve_end_point, end_point])
+                    .collect()
             }
+        };
+
+        Self::with_points((coming_from, going_to), exit, lane.speed_limit, points)
+    }
+
+    fn with_points((coming_from, going_to): Lane, exit: Direction, speed_limit: Option<f32>, points: Vec<Vec2>) -> Self {
+        let curvatures = (0..points.len())
+            .map(|index| match (index.checked_sub(1), points.get(index + 1)) {
+                (Some(previous), Some(&next)) => menger_curvature(points[previous], points[index], next),
+                _ => 0.0,
+            })
+            .collect();
+
+        Self {
+            coming_from,
+            going_to,
+            exit,
+            speed_limit,
+            points,
+            curvatures,
         }
     }
 
+    pub fn lane(&self) -> Lane {
+        (self.coming_from, self.going_to)
+    }
+
     pub fn points(&self) -> &Vec<Vec2> {
         &self.points
     }
@@ -181,4 +167,73 @@ impl Path {
     pub fn point(&self, index: usize) -> Option<Vec2> {
         self.points.get(index).copied()
     }
+
+    /// Highest speed at the point `index` keeping the lateral acceleration under
+    /// `lateral_acceleration`, in m/s, unbounded on straight parts
+    pub fn curve_speed(&self, index: usize, lateral_acceleration: f32) -> f32 {
+        match self.curvatures.get(index) {
+            Some(&curvature) if curvature > 1e-4 => (lateral_acceleration / curvature).sqrt(),
+            _ => f32::INFINITY,
+        }
+    }
+
+    /// Time to drive the polyline at `speed`, slowed down to the curve speed of the points
+    pub fn travel_time(&self, speed: f32, lateral_acceleration: f32) -> f32 {
+        self.points
+            .windows(2)
+            .enumerate()
+            .map(|(index, segment)| {
+                let speed = speed
+                    .min(self.curve_speed(index, lateral_acceleration))
+                    .min(self.curve_speed(index + 1, lateral_acceleration));
+                segment[0].distance(segment[1]) / speed.max(f32::EPSILON)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::MAX_LATERAL_ACCELERATION;
+    use crate::traffic::layout::Geometry;
+    use std::rc::Rc;
+
+    fn path(going_to: Going) -> Rc<Path> {
+        Geometry::default()
+            .paths
+            .into_iter()
+            .find(|path| path.lane() == (Direction::North, going_to))
+            .unwrap()
+    }
+
+    fn lowest_curve_speed(path: &Path) -> f32 {
+        (0..path.points().len())
+            .map(|index| path.curve_speed(index, MAX_LATERAL_ACCELERATION))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn straight_parts_are_not_slowed_down() {
+        let straight = path(Going::Straight);
+        for index in 0..straight.points().len() {
+            assert_eq!(straight.curve_speed(index, MAX_LATERAL_ACCELERATION), f32::INFINITY);
+        }
+
+        let right = path(Going::Right);
+        assert_eq!(right.curve_speed(0, MAX_LATERAL_ACCELERATION), f32::INFINITY);
+        assert_eq!(right.curve_speed(right.points().len() - 1, MAX_LATERAL_ACCELERATION), f32::INFINITY);
+    }
+
+    #[test]
+    fn tight_right_turns_are_slower_than_wide_left_ones() {
+        let (right, left) = (path(Going::Right), path(Going::Left));
+        assert!(lowest_curve_speed(&right) < lowest_curve_speed(&left));
+
+        // driven at a speed that only the curves bring down
+        let length = |path: &Path| path.points().windows(2).map(|segment| segment[0].distance(segment[1])).sum::<f32>();
+        let delay = |path: &Path| path.travel_time(100.0, MAX_LATERAL_ACCELERATION) - length(path) / 100.0;
+        assert!(delay(&right) > 0.0 && delay(&left) > 0.0);
+        assert!(delay(&path(Going::Straight)).abs() < 1e-4);
+    }
 }
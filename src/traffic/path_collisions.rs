@@ -1,5 +1,5 @@
 use crate::traffic::car::CarStatus::Turning;
-use crate::traffic::{Direction, Going, Path, TrafficState};
+use crate::traffic::{Path, TrafficState};
 use std::rc::Rc;
 
 impl Path {
@@ -16,83 +16,11 @@ impl Path {
 
     /// Returns all paths that intersecting with this path
     fn get_intersecting_paths(&self, traffic_state: &TrafficState) -> Vec<Rc<Path>> {
-        let path_definitions: Vec<(Direction, Going)> = match (self.coming_from, self.going_to) {
-            (_, Going::Right) => vec![],
-            (Direction::South, Going::Left) => {
-                vec![
-                    (Direction::West, Going::Straight),
-                    (Direction::East, Going::Left),
-                    (Direction::West, Going::Left),
-                    (Direction::North, Going::Straight),
-                ]
-            }
-            (Direction::South, Going::Straight) => {
-                vec![
-                    (Direction::West, Going::Straight),
-                    (Direction::North, Going::Left),
-                    (Direction::East, Going::Left),
-                    (Direction::East, Going::Straight),
-                ]
-            }
-            (Direction::North, Going::Left) => {
-                vec![
-                    (Direction::East, Going::Straight),
-                    (Direction::West, Going::Left),
-                    (Direction::East, Going::Left),
-                    (Direction::South, Going::Straight),
-                ]
-            }
-            (Direction::North, Going::Straight) => {
-                vec![
-                    (Direction::East, Going::Straight),
-                    (Direction::South, Going::Left),
-                    (Direction::West, Going::Left),
-                    (Direction::West, Going::Straight),
-                ]
-            }
-            (Direction::East, Going::Left) => {
-                vec![
-                    (Direction::South, Going::Straight),
-                    (Direction::North, Going::Left),
-                    (Direction::South, Going::Left),
-                    (Direction::West, Going::Straight),
-                ]
-            }
-            (Direction::East, Going::Straight) => {
-                vec![
-                    (Direction::South, Going::Straight),
-                    (Direction::West, Going::Left),
-                    (Direction::North, Going::Left),
-                    (Direction::North, Going::Straight),
-                ]
-            }
-            (Direction::West, Going::Left) => {
-                vec![
-                    (Direction::North, Going::Straight),
-                    (Direction::South, Going::Left),
-                    (Direction::North, Going::Left),
-                    (Direction::East, Going::Straight),
-                ]
-            }
-            (Direction::West, Going::Straight) => {
-                vec![
-                    (Direction::North, Going::Straight),
-                    (Direction::East, Going::Left),
-                    (Direction::South, Going::Left),
-                    (Direction::South, Going::Straight),
-                ]
-            }
-        };
+        let crossing_lanes = traffic_state.geometry.crossing_lanes(self.lane());
 
         traffic_state
-            .lines
-            .iter()
-            .flat_map(|line| line.paths.iter())
-            .filter(|path| {
-                path_definitions.iter().any(|&(coming_from, going_to)| {
-                    path.coming_from == coming_from && path.going_to == going_to
-                })
-            })
+            .paths()
+            .filter(|path| crossing_lanes.contains(&path.lane()))
             .cloned()
             .collect()
     }
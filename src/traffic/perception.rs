@@ -0,0 +1,264 @@
+use crate::constant::{CAR_LENGTH, TICK};
+use crate::traffic::car::CarStatus::Turning;
+use crate::traffic::{Car, Geometry, Line, Params, TrafficState};
+use macroquad::math::Vec2;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// On-board sensors of every car, read from the `[perception]` table of a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorConfig {
+    /// Farthest perceived car, in metres
+    pub range: f32,
+
+    /// Angle of view centred on the heading of the car, in degrees
+    pub field_of_view: f32,
+
+    /// Standard deviation of the perceived positions, in metres
+    pub position_noise: f32,
+
+    /// Standard deviation of the perceived speeds, in m/s
+    pub speed_noise: f32,
+
+    /// Seconds between a perception and the decision taken from it
+    pub reaction_time: f64,
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        SensorConfig {
+            range: 50.0,
+            field_of_view: 120.0,
+            position_noise: 0.0,
+            speed_noise: 0.0,
+            reaction_time: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PerceivedCar {
+    /// Free space to the perceived car, in metres
+    pub gap: f32,
+    pub velocity: f32,
+}
+
+/// What a car knows of its surroundings when deciding its speed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Perception {
+    /// Car ahead on the same lane, when seen
+    pub leader: Option<PerceivedCar>,
+
+    /// A car is seen crossing the intersection on a path intersecting the own path
+    pub crossing_traffic: bool,
+}
+
+/// Normal sample by the Box-Muller transform
+fn gaussian(rng: &mut StdRng, standard_deviation: f32) -> f32 {
+    if standard_deviation <= 0.0 {
+        return 0.0;
+    }
+
+    let (u, v) = (1.0 - rng.gen::<f32>(), rng.gen::<f32>());
+    standard_deviation * (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+impl SensorConfig {
+    /// Decisions are taken from the perception this many ticks old
+    pub fn reaction_ticks(&self) -> usize {
+        (self.reaction_time.max(0.0) / TICK).round() as usize
+    }
+
+    /// Noisy position of `other` as seen from `car`, if within range and field of view
+    fn sense(&self, car: &Car, other: &Car, rng: &mut StdRng) -> Option<Vec2> {
+        let position = other.center() + Vec2::new(gaussian(rng, self.position_noise), gaussian(rng, self.position_noise));
+        let offset = position - car.pos;
+        let heading = Vec2::from_angle(car.rotation);
+        let half_angle = (self.field_of_view / 2.0).to_radians();
+
+        let visible = offset.length() <= self.range && offset.normalize_or_zero().dot(heading) >= half_angle.cos();
+        visible.then_some(position)
+    }
+
+    pub fn perceive(
+        &self,
+        car: &Car,
+        leader: Option<&Car>,
+        lines: &[Line],
+        geometry: &Geometry,
+        params: &Params,
+        rng: &mut StdRng,
+    ) -> Perception {
+        let leader = leader.and_then(|leader| {
+            let position = self.sense(car, leader, rng)?;
+
+            Some(PerceivedCar {
+                gap: position.distance(car.pos) - CAR_LENGTH / 2.0,
+                velocity: (leader.velocity + gaussian(rng, self.speed_noise)).max(0.0),
+            })
+        });
+
+        let mut crossing_traffic = false;
+        if let Some(roundabout) = &geometry.roundabout {
+            for other in roundabout.circulating(car, lines, params) {
+                if self.sense(car, other, rng).is_some() {
+                    crossing_traffic = true;
+                }
+            }
+        } else {
+            for (coming_from, going_to) in geometry.crossing_lanes(car.path.lane()) {
+                for other in lines[coming_from as usize].cars_going(going_to) {
+                    if other.get_status() == Turning && self.sense(car, other, rng).is_some() {
+                        crossing_traffic = true;
+                    }
+                }
+            }
+        }
+
+        Perception {
+            leader,
+            crossing_traffic,
+        }
+    }
+}
+
+impl TrafficState {
+    /// Gives every car its perception of this tick, keeping the last `reaction_ticks`
+    pub fn update_perceptions(&mut self, sensors: SensorConfig) {
+        let mut perceptions = Vec::new();
+        for line in &self.lines {
+            for cars in &line.path_cars {
+                for (index, car) in cars.iter().enumerate() {
+                    let prev_car = index.checked_sub(1).map(|index| &cars[index]);
+                    let leader = line.leader(car, prev_car, &self.lines, &self.geometry);
+                    perceptions.push(sensors.perceive(car, leader, &self.lines, &self.geometry, &self.params, &mut self.rng));
+                }
+            }
+        }
+
+        let cars = self.lines.iter_mut().flat_map(|line| line.path_cars.iter_mut().flatten());
+        for (car, perception) in cars.zip(perceptions) {
+            car.perceptions.push_back(perception);
+            while car.perceptions.len() > sensors.reaction_ticks() + 1 {
+                car.perceptions.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{Direction, Going, Layout};
+    use rand::SeedableRng;
+    use std::rc::Rc;
+
+    /// One lane of the north approach allowing two movements, as imported from SUMO, with
+    /// room for a car behind one waiting at its stop line
+    fn shared_lane() -> TrafficState {
+        let layout: Layout = toml::from_str(
+            r#"lanes = [
+                { from = "North", movement = "Straight", points = [[30, 0], [30, 30], [30, 40], [30, 70]] },
+                { from = "North", movement = "Left", points = [[30, 0], [30, 30], [40, 40], [70, 40]] },
+            ]"#,
+        )
+        .unwrap();
+        let mut traffic_state = TrafficState::with_geometry(2, Rc::new(Geometry::new(&layout)));
+        traffic_state.sensors = Some(SensorConfig::default());
+        traffic_state
+    }
+
+    /// Car on the north straight lane of the default crossing, `distance` metres from its start
+    fn car_at(id: usize, distance: f32) -> Car {
+        let path = Geometry::default()
+            .paths
+            .iter()
+            .find(|path| path.lane() == (Direction::North, Going::Straight))
+            .unwrap()
+            .clone();
+        let mut car = Car::new(id, path, 0.0, 0.0);
+        car.pos += Vec2::from_angle(car.rotation) * distance;
+        car
+    }
+
+    #[test]
+    fn newborn_cars_face_their_lane() {
+        let car = car_at(0, 0.0);
+        assert!((car.rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sees_the_cars_in_range_and_in_the_field_of_view() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let sensors = SensorConfig { range: 20.0, field_of_view: 90.0, ..Default::default() };
+        let car = car_at(0, 0.0);
+
+        let ahead = car_at(1, 15.0);
+        assert_eq!(sensors.sense(&car, &ahead, &mut rng), Some(ahead.center()));
+        assert_eq!(sensors.sense(&car, &car_at(1, 25.0), &mut rng), None);
+
+        // a car 10 metres away, `angle` degrees from the heading
+        let aside = |angle: f32| {
+            let mut other = car_at(1, 0.0);
+            let heading = Vec2::from_angle(car.rotation);
+            other.pos = car.pos + Vec2::from_angle(car.rotation + angle.to_radians()) * 10.0 + heading * CAR_LENGTH / 2.0;
+            other
+        };
+        assert!(sensors.sense(&car, &aside(40.0), &mut rng).is_some());
+        assert!(sensors.sense(&car, &aside(-40.0), &mut rng).is_some());
+        assert!(sensors.sense(&car, &aside(50.0), &mut rng).is_none());
+        assert!(sensors.sense(&car, &car_at(1, -10.0), &mut rng).is_none());
+    }
+
+    #[test]
+    fn acts_on_the_perception_of_the_reaction_time_before() {
+        let mut traffic_state = TrafficState::with_geometry(3, Default::default());
+        traffic_state.lines[Direction::North as usize].gen_car(car_at(0, 15.0));
+        traffic_state.lines[Direction::North as usize].gen_car(car_at(1, 0.0));
+        let follower = |traffic_state: &TrafficState| traffic_state.cars().find(|car| car.id == 1).unwrap().clone();
+
+        let blind = SensorConfig { range: 5.0, reaction_time: 0.5, ..Default::default() };
+        assert_eq!(blind.reaction_ticks(), 30);
+        traffic_state.update_perceptions(blind);
+        assert_eq!(follower(&traffic_state).perceptions.front().unwrap().leader, None);
+
+        // the leader comes in range, and the follower acts on it 30 ticks later
+        let sensors = SensorConfig { range: 50.0, ..blind };
+        for _ in 0..30 {
+            traffic_state.update_perceptions(sensors);
+            let follower = follower(&traffic_state);
+            assert_eq!(follower.perceptions.front().unwrap().leader, None);
+            assert!(follower.perceptions.back().unwrap().leader.is_some());
+        }
+        traffic_state.update_perceptions(sensors);
+        let follower = follower(&traffic_state);
+        assert_eq!(follower.perceptions.len(), 31);
+        assert!(follower.perceptions.front().unwrap().leader.is_some());
+    }
+
+    #[test]
+    fn sees_the_car_ahead_with_another_movement_on_a_shared_lane() {
+        let mut traffic_state = shared_lane();
+        let paths = traffic_state.lines[Direction::North as usize].paths.clone();
+        let path = |movement| paths.iter().find(|path| path.going_to == movement).unwrap().clone();
+
+        // both cars wait at the stop line
+        traffic_state.external_control.admitted = Some(Default::default());
+        traffic_state.gen_car_on_path(&path(Going::Left));
+        for _ in 0..300 {
+            traffic_state.update();
+            if traffic_state.lines[Direction::North as usize].cars_going(Going::Straight).is_empty() {
+                traffic_state.gen_car_on_path(&path(Going::Straight));
+            }
+        }
+
+        let line = &traffic_state.lines[Direction::North as usize];
+        let (leader, follower) = (&line.cars_going(Going::Left)[0], &line.cars_going(Going::Straight)[0]);
+        let perceived = follower.perceptions.back().unwrap().leader.unwrap();
+        let gap = leader.center().distance(follower.pos) - CAR_LENGTH / 2.0;
+        assert!((perceived.gap - gap).abs() < 0.01, "{} != {}", perceived.gap, gap);
+        assert!(follower.velocity == 0.0 && leader.border_distance() - follower.border_distance() > CAR_LENGTH);
+    }
+}
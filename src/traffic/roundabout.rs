@@ -0,0 +1,285 @@
+use crate::constant::{CAR_LENGTH, CAR_WIDTH, ROAD_WIDTH, WORLD_SIZE};
+use crate::traffic::car::CarStatus::{BeforeTurn, Turning};
+use crate::traffic::path::{border_middle, inward, right_of};
+use crate::traffic::{Car, Direction, Going, LaneLayout, Line, Params, Path};
+use macroquad::math::Vec2;
+use serde::Deserialize;
+use std::f32::consts::{PI, TAU};
+
+/// Width of a circulating lane, in metres
+const LANE_WIDTH: f32 = ROAD_WIDTH / 4.0;
+
+/// Angle between the axis of a road and the points where its lanes join and leave the ring
+const ENTRY_ANGLE: f32 = PI / 6.0;
+
+/// Angle between two points of the ring, the same for every path so that their segments overlap
+const RING_STEP: f32 = TAU / 48.0;
+
+/// Roundabout centred on the map, replacing the lanes of a layout:
+///
+/// ```toml
+/// [layout.roundabout]
+/// radius = 18         # radius of the outer circulating lane, metres
+/// lanes = 2           # circulating lanes, 1 or 2
+/// critical_gap = 2    # seconds before a circulating car reaches an entry, needed to enter before it
+/// speed_limit = 10    # optional, m/s
+/// ```
+///
+/// Cars circulate counterclockwise and every approach leads to every exit: `Right` to the
+/// first one, `Straight` to the opposite road, `Left` to the third one and `UTurn` back to
+/// its own road. With two lanes, `Right` and `Straight` use the outer lane and `Left` and
+/// `UTurn` the inner one.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Roundabout {
+    pub radius: f32,
+    pub lanes: usize,
+    pub critical_gap: f32,
+    pub speed_limit: Option<f32>,
+}
+
+impl Default for Roundabout {
+    fn default() -> Self {
+        Roundabout {
+            radius: 18.0,
+            lanes: 1,
+            critical_gap: 2.0,
+            speed_limit: None,
+        }
+    }
+}
+
+fn centre() -> Vec2 {
+    Vec2::splat(WORLD_SIZE / 2.0)
+}
+
+/// Angle of `position` around the centre of the map
+fn angle(position: Vec2) -> f32 {
+    let offset = position - centre();
+    offset.y.atan2(offset.x)
+}
+
+/// Angle to cover from `from` to reach `to`, the angles decreasing counterclockwise on screen
+fn angle_between(from: f32, to: f32) -> f32 {
+    (from - to).rem_euclid(TAU)
+}
+
+/// Angles at which the path joins and leaves its ring, after its stop line and before its exit
+fn ring_ends(path: &Path) -> (f32, f32) {
+    let points = path.points();
+    (angle(points[2]), angle(points[points.len() - 3]))
+}
+
+/// Radius of the ring lane of the path
+fn ring_radius(path: &Path) -> f32 {
+    path.point(2).map_or(0.0, |point| point.distance(centre()))
+}
+
+impl Roundabout {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=2).contains(&self.lanes) {
+            return Err(format!("a roundabout has 1 or 2 lanes, not {}", self.lanes));
+        }
+
+        // an island as wide as a lane, and approaches two cars long
+        let min = LANE_WIDTH * self.lanes as f32;
+        let max = WORLD_SIZE / 2.0 - 2.0 * CAR_LENGTH - LANE_WIDTH / 2.0 - 1.0;
+        if !(min..=max).contains(&self.radius) {
+            return Err(format!(
+                "the radius of a roundabout with {} lanes must be between {:.1} and {:.1} m",
+                self.lanes, min, max
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Distance of the lanes of `movement` from the axis of their roads, and radius of their ring lane
+    fn lane(&self, movement: Going) -> (f32, f32) {
+        match (self.lanes, movement) {
+            (1, _) => (ROAD_WIDTH / 4.0, self.radius),
+            (_, Going::Right | Going::Straight) => (ROAD_WIDTH * 3.0 / 8.0, self.radius),
+            _ => (ROAD_WIDTH / 8.0, self.radius - LANE_WIDTH),
+        }
+    }
+
+    pub fn outer_edge(&self) -> f32 {
+        self.radius + LANE_WIDTH / 2.0
+    }
+
+    pub fn inner_edge(&self) -> f32 {
+        self.radius - LANE_WIDTH * (self.lanes as f32 - 0.5)
+    }
+
+    /// One lane per approach and movement, given by its points
+    pub fn lane_layouts(&self) -> Vec<LaneLayout> {
+        Direction::ALL
+            .into_iter()
+            .flat_map(|from| {
+                Going::ALL.map(|movement| LaneLayout {
+                    from,
+                    movement,
+                    offset: self.lane(movement).0,
+                    exit_offset: None,
+                    speed_limit: self.speed_limit,
+                    curvature: None,
+                    points: Some(self.points(from, movement)),
+                })
+            })
+            .collect()
+    }
+
+    fn points(&self, from: Direction, movement: Going) -> Vec<[f32; 2]> {
+        let to = from.destination(movement);
+        let (offset, ring) = self.lane(movement);
+
+        // stop line and end of the exit, just outside the ring
+        let stop = (self.outer_edge().powi(2) - offset.powi(2)).sqrt() + 1.0;
+        let start = border_middle(from) + right_of(inward(from)) * offset;
+        let end = border_middle(to) + right_of(-inward(to)) * offset;
+
+        let axis = |direction: Direction| angle(border_middle(direction));
+        let first = ((axis(from) - ENTRY_ANGLE) / RING_STEP).round() as i32;
+        let steps = (angle_between(axis(from) - ENTRY_ANGLE, axis(to) + ENTRY_ANGLE) / RING_STEP).round() as i32;
+
+        let mut points = vec![start, start + inward(from) * (WORLD_SIZE / 2.0 - stop)];
+        points.extend((0..=steps).map(|step| centre() + Vec2::from_angle((first - step) as f32 * RING_STEP) * ring));
+        points.extend([end + inward(to) * (WORLD_SIZE / 2.0 - stop), end]);
+
+        points.iter().map(|point| point.to_array()).collect()
+    }
+
+    /// Nearest car in front of `car`, whatever its lane, once `car` passed its stop line: the
+    /// paths overlap on the ring and loop back near their start. Of two cars each in front of
+    /// the other, only the one further behind follows.
+    pub fn leader<'a>(&self, car: &Car, lines: &'a [Line]) -> Option<&'a Car> {
+        if car.get_status() == BeforeTurn {
+            return None;
+        }
+
+        let ahead = |from: &Car, to: &Car| (to.pos - from.pos).dot(Vec2::from_angle(from.rotation));
+        lines
+            .iter()
+            .flat_map(|line| line.path_cars.iter().flatten())
+            .filter(|other| other.id != car.id && other.get_status() != BeforeTurn)
+            .filter(|other| {
+                let lateral = (other.pos - car.pos).perp_dot(Vec2::from_angle(car.rotation)).abs();
+                lateral < CAR_WIDTH && ahead(car, other) > 0.0 && ahead(car, other) > ahead(other, car)
+            })
+            .min_by(|a, b| a.pos.distance(car.pos).total_cmp(&b.pos.distance(car.pos)))
+    }
+
+    /// Cars in the ring that `car`, waiting at its stop line, must let pass: those about to
+    /// reach its entry and those that just passed it, on its ring lane or the lanes it crosses,
+    /// and every car of its ring lane when the lane is full
+    pub fn circulating<'a>(&self, car: &Car, lines: &'a [Line], params: &Params) -> Vec<&'a Car> {
+        let (entry, _) = ring_ends(&car.path);
+        let ring = ring_radius(&car.path);
+        let spacing = CAR_LENGTH + 2.0 * params.safe_distance;
+
+        // still circulating, not yet on the way to the exit
+        let on_ring = |other: &Car| other.point_index() + 3 < other.path.points().len();
+        let others = lines
+            .iter()
+            .flat_map(|line| line.path_cars.iter().flatten())
+            .filter(|other| other.id != car.id && other.get_status() == Turning)
+            .collect::<Vec<_>>();
+
+        // cars too close to each other all around the ring would each wait for the next one
+        let ring_cars = others
+            .iter()
+            .filter(|other| on_ring(other) && (ring_radius(&other.path) - ring).abs() < 0.1)
+            .copied()
+            .collect::<Vec<_>>();
+        if ring_cars.len() + 1 >= (TAU * ring / spacing).floor() as usize {
+            return ring_cars;
+        }
+
+        let (inner, outer) = (ring - CAR_WIDTH, self.outer_edge() + CAR_WIDTH / 2.0);
+        others
+            .into_iter()
+            .filter(|other| {
+                let radius = other.pos.distance(centre());
+                if !(inner..=outer).contains(&radius) {
+                    return false;
+                }
+
+                let position = angle(other.pos);
+                let upstream = angle_between(position, entry) * radius;
+                let downstream = angle_between(entry, position) * radius;
+
+                // cars leaving the ring before the entry do not cross it
+                let remaining = match on_ring(other) {
+                    true => angle_between(position, ring_ends(&other.path).1) * radius,
+                    false => 0.0,
+                };
+
+                let approaching = upstream < spacing + other.velocity * self.critical_gap && remaining + CAR_LENGTH >= upstream;
+                approaching || downstream < spacing
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{Geometry, Layout, TrafficState};
+    use std::rc::Rc;
+
+    /// Car of `path` at its point `index`, facing the next one
+    fn car_at(id: usize, path: &Rc<Path>, index: usize, velocity: f32) -> Car {
+        let mut snapshot = Car::new(id, path.clone(), 0.0, velocity).snapshot();
+        let (point, next) = (path.point(index).unwrap(), path.point(index + 1).unwrap());
+        snapshot.point_index = index;
+        snapshot.pos = point.into();
+        snapshot.rotation = (next - point).y.atan2((next - point).x);
+        Car::restore(&snapshot, path.clone())
+    }
+
+    fn add(state: &mut TrafficState, car: Car) {
+        for line in &mut state.lines {
+            if let Some(index) = line.paths.iter().position(|path| Rc::ptr_eq(path, &car.path)) {
+                line.path_cars[index].push(car);
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn entering_cars_yield_to_the_circulating_ones() {
+        let geometry = Rc::new(Geometry::new(&Layout::roundabout(Roundabout::default())));
+        let roundabout = geometry.roundabout.unwrap();
+        let path = |from: Direction, movement: Going| {
+            geometry.paths.iter().find(|path| path.lane() == (from, movement)).unwrap().clone()
+        };
+
+        // waiting just before its stop line
+        let entering = path(Direction::North, Going::Straight);
+        let (start, stop) = (entering.point(0).unwrap(), entering.point(1).unwrap());
+        let mut car = Car::new(0, entering.clone(), 0.0, 0.0);
+        car.pos = stop + (start - stop).normalize() * 2.0;
+
+        // two ring points before the entry of the waiting car, on a path going past it
+        let (circulating, entry) = geometry
+            .paths
+            .iter()
+            .find_map(|path| {
+                let entry = path
+                    .points()
+                    .iter()
+                    .position(|point| point.distance(entering.point(2).unwrap()) < 0.1)?;
+                (entry >= 4 && entry + 3 < path.points().len()).then(|| (path.clone(), entry))
+            })
+            .unwrap();
+
+        let mut state = TrafficState::with_geometry(0, geometry.clone());
+        assert!(roundabout.circulating(&car, &state.lines, &state.params).is_empty());
+        assert!(car.update_velocity(None, &state) > 0.0);
+
+        add(&mut state, car_at(1, &circulating, entry - 2, 5.0));
+        let yielded_to = roundabout.circulating(&car, &state.lines, &state.params);
+        assert_eq!(yielded_to.iter().map(|other| other.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(car.update_velocity(None, &state), 0.0);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::traffic::layout::Lane;
+use crate::traffic::{Direction, Network, Path};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+/// Weight of the last crossing in the mean crossing time of a lane
+const CROSSING_TIME_SMOOTHING: f64 = 0.2;
+
+/// Vehicles per hour going from a node to another one, read from a network file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TripDemand {
+    pub origin: usize,
+    pub destination: usize,
+    pub rate: f64,
+}
+
+/// Journey of a car through the network, to leave it at its destination node
+#[derive(Debug, Clone)]
+pub struct Trip {
+    pub destination: usize,
+
+    /// Side by which the car leaves each intersection left on its route
+    pub route: VecDeque<Direction>,
+
+    /// Simulated time at which the car entered the network
+    pub start_time: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    /// Plans the route again at every intersection with the current crossing times,
+    /// instead of keeping the route planned on entry
+    pub reroute: bool,
+}
+
+/// Crossing times of the lanes, the costs of the shortest routes
+#[derive(Debug, Clone, Default)]
+pub struct Routing {
+    pub config: RoutingConfig,
+
+    /// Mean time taken by the last cars to cross each lane, by intersection
+    crossing_times: HashMap<(usize, Lane), f64>,
+}
+
+impl Routing {
+    pub fn new(config: RoutingConfig) -> Routing {
+        Routing {
+            config,
+            crossing_times: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, node: usize, lane: Lane, time: f64) {
+        self.crossing_times
+            .entry((node, lane))
+            .and_modify(|mean| *mean += (time - *mean) * CROSSING_TIME_SMOOTHING)
+            .or_insert(time);
+    }
+
+    /// Measured crossing time of the lane of `path`, its free-flow time before any car crossed it
+    fn crossing_time(&self, network: &Network, node: usize, path: &Path) -> f64 {
+        if let Some(&time) = self.crossing_times.get(&(node, path.lane())) {
+            return time;
+        }
+
+        let intersection = &network.intersections[node];
+        let speed = path.speed_limit.map_or(intersection.params.max_speed, |limit| limit.min(intersection.params.max_speed));
+
+        path.travel_time(speed, intersection.params.lateral_acceleration) as f64
+    }
+
+    /// Fastest route from the approach `approach` of `node` out of the network through the
+    /// border of `destination`, as the sides by which the car leaves each intersection,
+    /// with its expected duration in seconds
+    pub fn route(&self, network: &Network, node: usize, approach: Direction, destination: usize) -> Option<(VecDeque<Direction>, f64)> {
+        // Dijkstra over the approaches of the intersections, few enough to scan them all
+        let legs = Direction::LEGS.len();
+        let state = |node: usize, approach: Direction| node * legs + approach as usize;
+        let count = network.intersections.len() * legs;
+
+        let mut costs = vec![f64::INFINITY; count];
+        let mut previous: Vec<Option<(usize, Direction)>> = vec![None; count];
+        let mut done = vec![false; count];
+        let mut best_exit: Option<(f64, usize, Direction)> = None;
+        costs[state(node, approach)] = 0.0;
+
+        while let Some(current) = (0..count)
+            .filter(|&current| !done[current] && costs[current].is_finite())
+            .min_by(|&a, &b| costs[a].total_cmp(&costs[b]))
+        {
+            done[current] = true;
+            let (node, approach) = (current / legs, Direction::LEGS[current % legs]);
+            if best_exit.is_some_and(|(cost, ..)| cost <= costs[current]) {
+                break;
+            }
+
+            // every movement of the approach, to the leg its path leaves by
+            for path in &network.intersections[node].lines[approach as usize].paths {
+                let cost = costs[current] + self.crossing_time(network, node, path);
+                let side = path.exit;
+
+                match network.entry(node, side) {
+                    Some(next) => {
+                        let next = state(next, side.opposite());
+                        if cost < costs[next] {
+                            costs[next] = cost;
+                            previous[next] = Some((current, side));
+                        }
+                    }
+                    None if node == destination && best_exit.is_none_or(|(best, ..)| cost < best) => {
+                        best_exit = Some((cost, current, side));
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let (cost, mut current, side) = best_exit?;
+        let mut route = VecDeque::from([side]);
+        while let Some((before, side)) = previous[current] {
+            route.push_front(side);
+            current = before;
+        }
+
+        Some((route, cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{Geometry, Going, Layout, TrafficState};
+    use std::rc::Rc;
+
+    /// Five legs, the south-east one reaching the east leg by a right turn and the north
+    /// one by a slight right turn
+    const FIVE_LEGS: &str = r#"
+        lanes = [
+            { from = "SouthEast", movement = "Left", offset = 1.5 },
+            { from = "SouthEast", movement = "SlightLeft", offset = 4 },
+            { from = "SouthEast", movement = "SlightRight", offset = 9 },
+            { from = "SouthEast", movement = "Right", offset = 12 },
+            { from = "West", movement = "SlightLeft", offset = 4 },
+            { from = "West", movement = "Right", offset = 12 },
+        ]
+
+        [angles]
+        North = 0
+        East = 72
+        SouthEast = 144
+        SouthWest = 216
+        West = 288
+    "#;
+
+    /// The five-leg junction, with a square crossing linked to its east leg
+    fn network() -> Network {
+        let layout: Layout = toml::from_str(FIVE_LEGS).unwrap();
+        layout.validate().unwrap();
+        let geometries = [Rc::new(Geometry::new(&layout)), Rc::new(Geometry::default())];
+
+        Network::grid(2, 1, |index| TrafficState::with_geometry(7, geometries[index].clone()))
+    }
+
+    #[test]
+    fn routes_on_the_square_crossings_of_a_grid() {
+        let network = Network::grid(3, 1, |index| TrafficState::with_geometry(index as u64, Default::default()));
+        let routing = Routing::default();
+
+        let (route, cost) = routing.route(&network, 0, Direction::West, 2).unwrap();
+        assert_eq!(&route.iter().take(2).copied().collect::<Vec<_>>(), &[Direction::East, Direction::East]);
+        assert_eq!(route.len(), 3);
+        assert_ne!(route[2], Direction::West);
+        assert!(cost > 0.0);
+
+        // leaving back through the origin node
+        let (route, _) = routing.route(&network, 0, Direction::West, 0).unwrap();
+        assert_eq!(route.len(), 1);
+        assert!(network.entry(0, route[0]).is_none());
+    }
+
+    #[test]
+    fn routes_from_an_intercardinal_leg() {
+        let network = network();
+        let routing = Routing::default();
+
+        let (route, _) = routing.route(&network, 0, Direction::SouthEast, 1).unwrap();
+        assert_eq!(route.front(), Some(&Direction::East));
+        assert_eq!(route.len(), 2);
+
+        let (route, _) = routing.route(&network, 0, Direction::SouthEast, 0).unwrap();
+        assert_eq!(route.len(), 1);
+        assert_ne!(route[0], Direction::East);
+
+        // from the west leg, only the slight left turn reaches the east leg
+        let (route, _) = routing.route(&network, 0, Direction::West, 1).unwrap();
+        assert_eq!(route.front(), Some(&Direction::East));
+        assert!(network.intersections[0].lines[Direction::West as usize]
+            .paths
+            .iter()
+            .any(|path| path.going_to == Going::SlightLeft && path.exit == Direction::East));
+    }
+
+    #[test]
+    fn prefers_the_measured_faster_movement() {
+        let network = network();
+        let mut routing = Routing::default();
+
+        routing.record(0, (Direction::SouthEast, Going::Left), 1000.0);
+        routing.record(0, (Direction::SouthEast, Going::SlightLeft), 1000.0);
+        let (route, cost) = routing.route(&network, 0, Direction::SouthEast, 0).unwrap();
+        assert_eq!(route, [Direction::North]);
+        assert!(cost < 1000.0);
+
+        routing.record(0, (Direction::SouthEast, Going::SlightRight), 5000.0);
+        let (route, _) = routing.route(&network, 0, Direction::SouthEast, 0).unwrap();
+        assert!(route == [Direction::SouthWest] || route == [Direction::West]);
+    }
+
+    #[test]
+    fn trips_cross_an_angled_layout() {
+        let mut network = network();
+        network.demand = vec![
+            TripDemand {
+                origin: 0,
+                destination: 1,
+                rate: 1200.0,
+            },
+            TripDemand {
+                origin: 1,
+                destination: 0,
+                rate: 1200.0,
+            },
+        ];
+
+        for _ in 0..60 * 120 {
+            network.update();
+        }
+
+        assert!(network.transfers > 0);
+        assert!(!network.trip_times.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::app::Statistics;
+use crate::traffic::{Car, CarSnapshot, Geometry, LaneChangeConfig, Params, SensorConfig, TrafficState, V2i};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Complete state of the simulation, saved as JSON to reopen a situation later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub time: f64,
+    pub params: Params,
+    pub next_car_id: usize,
+
+    /// Seed of the random generator after the restore, so that reloading the
+    /// same snapshot always plays the same future
+    pub seed: u64,
+
+    /// `Geometry::fingerprint` of the lanes of the cars, missing in older snapshots
+    #[serde(default)]
+    pub layout: Option<u64>,
+
+    /// Cars of every lane, each lane keeping its order
+    pub cars: Vec<CarSnapshot>,
+    pub statistics: Statistics,
+
+    /// Messaging with its reservations and its messages in flight
+    #[serde(default)]
+    pub v2i: Option<V2i>,
+    #[serde(default)]
+    pub sensors: Option<SensorConfig>,
+    #[serde(default)]
+    pub lane_change: Option<LaneChangeConfig>,
+}
+
+impl TrafficState {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            time: self.time,
+            params: self.params,
+            next_car_id: self.next_car_id,
+            seed: self.rng.clone().gen(),
+            layout: Some(self.geometry.fingerprint()),
+            cars: self.cars().map(Car::snapshot).collect(),
+            statistics: self.statistics.clone(),
+            v2i: self.v2i.clone(),
+            sensors: self.sensors,
+            lane_change: self.lane_change,
+        }
+    }
+
+    /// State of the snapshot on the lanes of `geometry`, which must be those it was taken on
+    pub fn restore(snapshot: &Snapshot, geometry: Rc<Geometry>) -> Result<TrafficState, String> {
+        snapshot.check(&geometry)?;
+
+        let mut traffic_state = TrafficState::with_geometry(snapshot.seed, geometry);
+        traffic_state.time = snapshot.time;
+        traffic_state.params = snapshot.params;
+        traffic_state.next_car_id = snapshot.next_car_id;
+        traffic_state.statistics = snapshot.statistics.clone();
+        traffic_state.v2i = snapshot.v2i.clone();
+        traffic_state.sensors = snapshot.sensors;
+        traffic_state.lane_change = snapshot.lane_change;
+
+        for car in &snapshot.cars {
+            let line = &mut traffic_state.lines[car.coming_from as usize];
+            if let Some(path) = line.paths.iter().find(|path| path.going_to == car.going_to).cloned() {
+                line.gen_car(Car::restore(car, path));
+            }
+        }
+
+        Ok(traffic_state)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(&self.snapshot()).map_err(|error| error.to_string())?;
+
+        std::fs::write(path, json).map_err(|error| format!("cannot write {}: {}", path.display(), error))
+    }
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Snapshot, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+
+        serde_json::from_str(&json).map_err(|error| format!("invalid snapshot {}: {}", path.display(), error))
+    }
+
+    /// Fails unless every car of the snapshot has its lane in `geometry`, and the lanes
+    /// are the ones the snapshot was taken on
+    pub fn check(&self, geometry: &Geometry) -> Result<(), String> {
+        if self.layout.is_some_and(|layout| layout != geometry.fingerprint()) {
+            return Err("the snapshot was taken on other lanes, give the same layout".to_string());
+        }
+
+        match self.cars.iter().find(|car| !geometry.paths.iter().any(|path| path.lane() == (car.coming_from, car.going_to))) {
+            Some(car) => Err(format!("no {:?} lane on the {:?} approach for car {}", car.going_to, car.coming_from, car.id)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::TICK;
+    use crate::traffic::{Direction, Going, Layout};
+
+    /// Ten simulated seconds of random traffic on the default crossing
+    fn played() -> TrafficState {
+        let mut traffic_state = TrafficState::with_geometry(11, Default::default());
+        for tick in 0..(10.0 / TICK) as usize {
+            if tick % 20 == 0 {
+                traffic_state.gen_car_random();
+            }
+            traffic_state.update();
+        }
+        traffic_state
+    }
+
+    /// JSON of a snapshot, without the seed drawn for the future of the state
+    fn json(snapshot: &Snapshot) -> serde_json::Value {
+        let mut json = serde_json::to_value(snapshot).unwrap();
+        json.as_object_mut().unwrap().remove("seed");
+        json
+    }
+
+    #[test]
+    fn restores_a_snapshot_read_back_from_json() {
+        let traffic_state = played();
+        let snapshot = traffic_state.snapshot();
+        assert!(!snapshot.cars.is_empty());
+
+        let read: Snapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let restored = TrafficState::restore(&read, Default::default()).unwrap();
+
+        assert_eq!(json(&restored.snapshot()), json(&snapshot));
+        assert_eq!(restored.cars().count(), traffic_state.cars().count());
+        assert_eq!(restored.statistics.speed_samples.len(), traffic_state.statistics.speed_samples.len());
+    }
+
+    #[test]
+    fn replays_the_same_future_from_a_snapshot() {
+        let snapshot = played().snapshot();
+        let future = || {
+            let mut traffic_state = TrafficState::restore(&snapshot, Default::default()).unwrap();
+            for _ in 0..(5.0 / TICK) as usize {
+                traffic_state.gen_car_random();
+                traffic_state.update();
+            }
+            json(&traffic_state.snapshot())
+        };
+
+        assert_eq!(future(), future());
+    }
+
+    #[test]
+    fn refuses_other_lanes() {
+        let snapshot = played().snapshot();
+        let layout: Layout =
+            toml::from_str(r#"lanes = [{ from = "North", movement = "Straight", offset = 6.75 }]"#).unwrap();
+
+        let error = TrafficState::restore(&snapshot, Rc::new(Geometry::new(&layout))).unwrap_err();
+        assert_eq!(error, "the snapshot was taken on other lanes, give the same layout");
+
+        // older snapshots, without the fingerprint of their lanes
+        let older = Snapshot { layout: None, ..snapshot.clone() };
+        let error = TrafficState::restore(&older, Rc::new(Geometry::new(&layout))).unwrap_err();
+        let car = snapshot.cars.iter().find(|car| (car.coming_from, car.going_to) != (Direction::North, Going::Straight)).unwrap();
+        assert_eq!(error, format!("no {:?} lane on the {:?} approach for car {}", car.going_to, car.coming_from, car.id));
+        assert!(TrafficState::restore(&older, Default::default()).is_ok());
+    }
+
+    #[test]
+    fn keeps_the_messaging_sensors_and_lane_changes() {
+        let mut traffic_state = TrafficState::with_geometry(5, Default::default());
+        traffic_state.v2i = Some(V2i::new(Default::default()));
+        traffic_state.sensors = Some(SensorConfig { reaction_time: 0.2, ..Default::default() });
+        traffic_state.lane_change = Some(LaneChangeConfig { politeness: 0.5, ..Default::default() });
+        for tick in 0..(10.0 / TICK) as usize {
+            if tick % 20 == 0 {
+                traffic_state.gen_car_random();
+            }
+            traffic_state.update();
+        }
+        let snapshot = traffic_state.snapshot();
+        assert!(snapshot.cars.iter().any(|car| car.granted));
+        assert!(snapshot.cars.iter().any(|car| !car.perceptions.is_empty()));
+
+        let read: Snapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let restored = TrafficState::restore(&read, Default::default()).unwrap();
+        assert_eq!(restored.sensors, traffic_state.sensors);
+        assert_eq!(restored.lane_change, traffic_state.lane_change);
+        assert_eq!(
+            serde_json::to_value(&restored.v2i).unwrap(),
+            serde_json::to_value(&traffic_state.v2i).unwrap()
+        );
+        assert_eq!(json(&restored.snapshot()), json(&snapshot));
+    }
+}
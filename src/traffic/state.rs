@@ -1,63 +1,170 @@
-use crate::traffic::{Direction, Line, Path};
+use crate::constant::TICK;
+use crate::traffic::{Car, Direction, ExternalControl, Geometry, LaneChangeConfig, Line, Params, Path, SensorConfig, V2i};
 use crate::app::Statistics;
-use macroquad::prelude::get_time;
-use macroquad::rand::ChooseRandom;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct TrafficState {
-    pub lines: [Line; 4],
+    pub lines: [Line; 8],
     pub statistics: Statistics,
-    pub pause_time: f64,
+    pub params: Params,
+
+    /// Simulated time in seconds, frozen while paused
+    pub time: f64,
+
+    pub rng: StdRng,
+
+    /// Id given to the next spawned car
+    pub next_car_id: usize,
+
+    pub external_control: ExternalControl,
+
+    /// Messaging between the cars and the intersection, which otherwise see the whole state
+    pub v2i: Option<V2i>,
+
+    /// Sensors of the cars, which otherwise know the exact position of every car
+    pub sensors: Option<SensorConfig>,
+
+    /// Lane changes on the approaches, the cars being otherwise born on the lane of their movement
+    pub lane_change: Option<LaneChangeConfig>,
+
+    /// Lanes of the intersection, shared by the clones of the state
+    pub geometry: Rc<Geometry>,
 }
 
 impl TrafficState {
-    pub fn new() -> TrafficState {
+    pub fn with_geometry(seed: u64, geometry: Rc<Geometry>) -> TrafficState {
         TrafficState {
-            lines: [
-                Line::new(Direction::North),
-                Line::new(Direction::East),
-                Line::new(Direction::South),
-                Line::new(Direction::West),
-            ],
+            lines: Direction::LEGS.map(|direction| {
+                Line::new(
+                    geometry
+                        .paths
+                        .iter()
+                        .filter(|path| path.coming_from == direction)
+                        .cloned()
+                        .collect(),
+                )
+            }),
             statistics: Statistics::default(),
-            pause_time: get_time(),
+            params: Params::default(),
+            time: 0.0,
+            rng: StdRng::seed_from_u64(seed),
+            next_car_id: 0,
+            external_control: ExternalControl::default(),
+            v2i: None,
+            sensors: None,
+            lane_change: None,
+            geometry,
         }
     }
 
     pub fn toggle_pause(&mut self) {
         self.statistics.is_open = !self.statistics.is_open;
-
-        if self.statistics.is_open {
-            self.pause_time = get_time();
-        } else {
-            let pause_duration = get_time() - self.pause_time;
-            self.lines.iter_mut().for_each(|line| {
-                line.path_cars.iter_mut().for_each(|cars| {
-                    cars.iter_mut().for_each(|car| {
-                        car.start_time += pause_duration;
-                    });
-                });
-            });
-        }
     }
 
     pub fn update(&mut self) {
+        if let Some(v2i) = &mut self.v2i {
+            v2i.deliver(self.time, &mut self.lines, &self.geometry, &mut self.rng);
+        }
+
+        if let Some(sensors) = self.sensors {
+            self.update_perceptions(sensors);
+        }
+
+        if let Some(lane_change) = self.lane_change {
+            for line in &mut self.lines {
+                line.change_lanes(&lane_change, &self.params);
+            }
+        }
+
+        // the lines only read the cars of the clone, whose series would grow the copy every tick
+        let series = self.statistics.take_series();
         let traffic_state = self.clone();
+        self.statistics.put_series(series);
 
         for line in &mut self.lines {
             line.update(&traffic_state);
         }
 
+        if let Some(v2i) = &mut self.v2i {
+            v2i.send_car_messages(self.time, &self.lines, &self.params, &mut self.rng);
+        }
+
         self.statistics.update(&traffic_state);
+        self.time += TICK;
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Rc<Path>> {
+        self.lines.iter().flat_map(|line| line.paths.iter())
+    }
+
+    /// Approaches with lanes, in the order of `Direction::LEGS`
+    pub fn approaches(&self) -> Vec<Direction> {
+        Direction::LEGS
+            .into_iter()
+            .filter(|&direction| !self.lines[direction as usize].paths.is_empty())
+            .collect()
+    }
+
+    /// Waiting cars on each approach with lanes, as shown in the status lines
+    pub fn queue_summary(&self) -> String {
+        self.approaches()
+            .into_iter()
+            .map(|direction| {
+                let queue = self.lines[direction as usize].queue_length(&self.params);
+                format!("{} {}", direction.abbreviation(), queue)
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+
+    pub fn cars(&self) -> impl Iterator<Item = &Car> {
+        self.lines
+            .iter()
+            .flat_map(|line| line.path_cars.iter())
+            .flatten()
+    }
+
+    fn new_car(&mut self, path: Rc<Path>) -> Car {
+        self.next_car_id += 1;
+
+        let velocity = path.speed_limit.map_or(self.params.max_speed, |limit| limit.min(self.params.max_speed));
+        Car::new(self.next_car_id - 1, path, self.time, velocity)
     }
 
     pub fn gen_car(&mut self, coming_from: Direction) {
         self.statistics.car_count += 1;
-        let line = &mut self.lines[coming_from as usize];
+        let line = &self.lines[coming_from as usize];
 
-        if let Some(path) = line.get_free_paths().choose() {
-            line.gen_car(path.clone());
+        // with lane changes, any movement of the approach, born on any free lane
+        let movement = match self.lane_change {
+            Some(_) => line.paths.iter().map(|path| path.going_to).collect::<Vec<_>>().choose(&mut self.rng).copied(),
+            None => None,
+        };
+
+        if let Some(path) = line.get_free_paths(&self.params).choose(&mut self.rng) {
+            let path = movement.map_or(path.clone(), |movement| line.path_on_lane(path, movement));
+            let mut car = self.new_car(path);
+            car.movement = movement.unwrap_or(car.movement);
+            self.lines[coming_from as usize].gen_car(car);
+        }
+    }
+
+    /// Spawns a car on the given lane if its entrance is free
+    pub fn gen_car_on_path(&mut self, path: &Path) {
+        let line = &self.lines[path.coming_from as usize];
+
+        if let Some(path) = line
+            .get_free_paths(&self.params)
+            .into_iter()
+            .find(|free_path| free_path.going_to == path.going_to)
+        {
+            let car = self.new_car(path.clone());
+            self.lines[path.coming_from as usize].gen_car(car);
+            self.statistics.car_count += 1;
         }
     }
 
@@ -66,15 +173,16 @@ impl TrafficState {
             .iter()
             .enumerate()
             .flat_map(|(line_index, line)| {
-                line.get_free_paths()
+                line.get_free_paths(&self.params)
                     .iter()
                     .map(move |path| (line_index, path.clone()))
                     .collect::<Vec<_>>()
             })
             .collect();
 
-        if let Some((line_index, path)) = available_line_paths.choose() {
-            self.lines[*line_index].gen_car(path.clone());
+        if let Some((line_index, path)) = available_line_paths.choose(&mut self.rng).cloned() {
+            let car = self.new_car(path);
+            self.lines[line_index].gen_car(car);
             self.statistics.car_count += 1;
         }
     }
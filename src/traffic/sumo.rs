@@ -0,0 +1,354 @@
+use crate::constant::WORLD_SIZE;
+use crate::traffic::path::inward;
+use crate::traffic::{Direction, Going, LaneLayout, Layout};
+use macroquad::math::Vec2;
+use roxmltree::{Document, Node};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Lane of a SUMO network, its shape already in world coordinates
+struct SumoLane {
+    shape: Vec<Vec2>,
+    speed: f32,
+}
+
+/// Connection of an incoming lane to an outgoing one through the junction
+struct Connection {
+    from_lane: String,
+    index: usize,
+    internal: Vec<String>,
+    to_lane: String,
+    movement: Going,
+}
+
+/// Layout of the junction `junction` of a SUMO `.net.xml` file, the only junction of the
+/// file when none is given. The junction is centred on the map, its approaches keep the
+/// part of their lanes inside it, and each approach gets one lane per movement: the
+/// rightmost lane turning right, the leftmost turning left and the middle one of the
+/// others going straight. U-turns are ignored.
+pub fn import_sumo(path: &Path, junction: Option<&str>) -> Result<Layout, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    let document =
+        Document::parse(&content).map_err(|error| format!("invalid SUMO network {}: {}", path.display(), error))?;
+
+    junction_layout(&document, junction).map_err(|error| format!("invalid SUMO network {}: {}", path.display(), error))
+}
+
+fn junction_layout(document: &Document, junction: Option<&str>) -> Result<Layout, String> {
+    let junction = find_junction(document, junction)?;
+    let id = junction.attribute("id").unwrap_or_default();
+    let centre = Vec2::new(number(junction, "x")?, number(junction, "y")?);
+
+    // SUMO has its y axis pointing up, the map down
+    let to_world = |x: f32, y: f32| Vec2::new(x - centre.x, centre.y - y) + Vec2::splat(WORLD_SIZE / 2.0);
+
+    let mut lanes = HashMap::new();
+    let mut incoming = HashSet::new();
+    for edge in document.root_element().children().filter(|node| node.has_tag_name("edge")) {
+        for lane in edge.children().filter(|node| node.has_tag_name("lane")) {
+            let lane_id = lane.attribute("id").unwrap_or_default().to_string();
+            let shape = lane
+                .attribute("shape")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(|point| {
+                    let (x, y) = point.split_once(',').ok_or_else(|| format!("invalid shape of lane `{}`", lane_id))?;
+                    match (x.parse(), y.parse()) {
+                        (Ok(x), Ok(y)) => Ok(to_world(x, y)),
+                        _ => Err(format!("invalid shape of lane `{}`", lane_id)),
+                    }
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            if shape.len() < 2 {
+                return Err(format!("lane `{}` has no shape", lane_id));
+            }
+
+            if edge.attribute("to") == Some(id) && edge.attribute("function") != Some("internal") {
+                incoming.insert(lane_id.clone());
+            }
+            lanes.insert(lane_id, SumoLane { shape, speed: number(lane, "speed")? });
+        }
+    }
+
+    let connections = connections(document, &incoming);
+
+    let mut layout_lanes = Vec::new();
+    let mut approaches: HashMap<(Direction, Going), Vec<&Connection>> = HashMap::new();
+    for connection in &connections {
+        let shape = &lanes[&connection.from_lane].shape;
+        let heading = shape[shape.len() - 1] - shape[shape.len() - 2];
+        let from = Direction::ALL
+            .into_iter()
+            .max_by(|a, b| inward(*a).dot(heading).total_cmp(&inward(*b).dot(heading)))
+            .unwrap_or(Direction::North);
+
+        approaches.entry((from, connection.movement)).or_default().push(connection);
+    }
+
+    let mut selected = Vec::new();
+    for from in Direction::ALL {
+        let candidates = |movement| {
+            let mut candidates = approaches.get(&(from, movement)).cloned().unwrap_or_default();
+            candidates.sort_by_key(|connection| connection.index);
+            candidates
+        };
+        let right = candidates(Going::Right).first().copied();
+        let left = candidates(Going::Left).last().copied();
+
+        // straight ahead on a lane of its own when there is one, so that two spawned
+        // cars never share their first point
+        let straight = candidates(Going::Straight);
+        let own = straight
+            .iter()
+            .filter(|connection| [right, left].iter().flatten().all(|turn| turn.from_lane != connection.from_lane))
+            .copied()
+            .collect::<Vec<_>>();
+        let straight = match own.is_empty() {
+            true => straight.get((straight.len().max(1) - 1) / 2).copied(),
+            false => Some(own[(own.len() - 1) / 2]),
+        };
+
+        selected.extend([(from, Going::Straight, straight), (from, Going::Left, left), (from, Going::Right, right)]);
+    }
+
+    for (from, movement, connection) in selected {
+        let Some(connection) = connection else { continue };
+
+        let lane = |id: &String| lanes.get(id).ok_or_else(|| format!("unknown lane `{}`", id));
+        let approach = lane(&connection.from_lane)?;
+        let exit = lane(&connection.to_lane)?;
+
+        let mut points = vec![inside_start(&approach.shape)];
+        points.push(approach.shape[approach.shape.len() - 1]);
+        for internal in &connection.internal {
+            points.extend(lane(internal)?.shape.iter().copied());
+        }
+        points.push(exit.shape[0]);
+        points.push(inside_end(&exit.shape));
+        points.dedup_by(|a, b| a.distance(*b) < 1e-3);
+
+        layout_lanes.push(LaneLayout {
+            from,
+            movement,
+            offset: 0.0,
+            exit_offset: None,
+            speed_limit: Some(approach.speed),
+            curvature: None,
+            points: Some(points.iter().map(|point| point.to_array()).collect()),
+        });
+    }
+
+    let layout = Layout {
+        approach_length: 0.0,
+        lanes: layout_lanes,
+        roundabout: None,
+        angles: HashMap::new(),
+    };
+    layout.validate()?;
+
+    Ok(layout)
+}
+
+fn find_junction<'a>(document: &'a Document, junction: Option<&str>) -> Result<Node<'a, 'a>, String> {
+    let mut junctions = document.root_element().children().filter(|node| {
+        node.has_tag_name("junction") && !matches!(node.attribute("type"), Some("internal" | "dead_end"))
+    });
+
+    match junction {
+        Some(id) => junctions
+            .find(|node| node.attribute("id") == Some(id))
+            .ok_or_else(|| format!("no junction `{}`", id)),
+        None => {
+            let junctions = junctions.collect::<Vec<_>>();
+            match junctions[..] {
+                [junction] => Ok(junction),
+                [] => Err("no junction".to_string()),
+                _ => Err(format!(
+                    "several junctions, choose one of {}",
+                    junctions
+                        .iter()
+                        .map(|node| node.attribute("id").unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            }
+        }
+    }
+}
+
+/// Connections leaving the `incoming` lanes, their internal lanes followed to the end
+fn connections(document: &Document, incoming: &HashSet<String>) -> Vec<Connection> {
+    let all = document
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("connection"))
+        .collect::<Vec<_>>();
+
+    let mut connections = Vec::new();
+    for node in &all {
+        let from_lane = lane_id(*node, "from", "fromLane");
+        if !incoming.contains(&from_lane) {
+            continue;
+        }
+
+        let movement = match node.attribute("dir") {
+            Some("s") => Going::Straight,
+            Some("l" | "L") => Going::Left,
+            Some("r" | "R") => Going::Right,
+            _ => continue,
+        };
+
+        let mut internal = Vec::new();
+        let mut via = node.attribute("via");
+        while let Some(lane) = via {
+            internal.push(lane.to_string());
+            // an internal lane may continue through another one, after an internal junction
+            via = all
+                .iter()
+                .find(|next| lane_id(**next, "from", "fromLane") == lane)
+                .and_then(|next| next.attribute("via"));
+        }
+
+        connections.push(Connection {
+            index: node.attribute("fromLane").and_then(|index| index.parse().ok()).unwrap_or(0),
+            from_lane,
+            internal,
+            to_lane: lane_id(*node, "to", "toLane"),
+            movement,
+        });
+    }
+
+    connections
+}
+
+/// Id of the lane `lane` of the edge `edge`, both attributes of a connection
+fn lane_id(node: Node, edge: &str, lane: &str) -> String {
+    format!("{}_{}", node.attribute(edge).unwrap_or_default(), node.attribute(lane).unwrap_or_default())
+}
+
+fn number(node: Node, attribute: &str) -> Result<f32, String> {
+    node.attribute(attribute)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("invalid `{}` of `{}`", attribute, node.attribute("id").unwrap_or_default()))
+}
+
+fn is_inside(point: Vec2) -> bool {
+    (0.0..=WORLD_SIZE).contains(&point.x) && (0.0..=WORLD_SIZE).contains(&point.y)
+}
+
+/// First point of the lane inside the map, walking back from its end
+fn inside_start(shape: &[Vec2]) -> Vec2 {
+    let reversed = shape.iter().rev().copied().collect::<Vec<_>>();
+    inside_end(&reversed)
+}
+
+/// Last point of the lane inside the map, walking from its start
+fn inside_end(shape: &[Vec2]) -> Vec2 {
+    let mut last = shape[0];
+    for &point in &shape[1..] {
+        if !is_inside(point) {
+            return border_crossing(last, point);
+        }
+        last = point;
+    }
+
+    last
+}
+
+/// Point where the segment from `inside` to `outside` leaves the map
+fn border_crossing(inside: Vec2, outside: Vec2) -> Vec2 {
+    let direction = outside - inside;
+    let exit = |position: f32, delta: f32| match delta {
+        delta if delta > 0.0 => (WORLD_SIZE - position) / delta,
+        delta if delta < 0.0 => -position / delta,
+        _ => f32::INFINITY,
+    };
+
+    let t = exit(inside.x, direction.x).min(exit(inside.y, direction.y)).clamp(0.0, 1.0);
+    inside + direction * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A T junction at (100, 100) entered from the west, with one internal lane going straight
+    const T_JUNCTION: &str = r#"
+        <net>
+            <edge id=":C_0" function="internal">
+                <lane id=":C_0_0" index="0" speed="13.89" shape="90,98.4 110,98.4"/>
+            </edge>
+            <edge id="W2C" from="W" to="C">
+                <lane id="W2C_0" index="0" speed="13.89" shape="0,98.4 90,98.4"/>
+            </edge>
+            <edge id="C2E" from="C" to="E">
+                <lane id="C2E_0" index="0" speed="13.89" shape="110,98.4 200,98.4"/>
+            </edge>
+            <edge id="C2N" from="C" to="N">
+                <lane id="C2N_0" index="0" speed="8.33" shape="101.6,110 101.6,200"/>
+            </edge>
+            <edge id="C2S" from="C" to="S">
+                <lane id="C2S_0" index="0" speed="8.33" shape="98.4,90 98.4,0"/>
+            </edge>
+            <junction id="W" type="dead_end" x="0" y="100"/>
+            <junction id="C" type="priority" x="100" y="100"/>
+            <junction id=":C_0_0" type="internal" x="100" y="98.4"/>
+            <connection from="W2C" to="C2E" fromLane="0" toLane="0" via=":C_0_0" dir="s"/>
+            <connection from="W2C" to="C2N" fromLane="0" toLane="0" dir="l"/>
+            <connection from="W2C" to="C2S" fromLane="0" toLane="0" dir="r"/>
+            <connection from="W2C" to="W2C" fromLane="0" toLane="0" dir="t"/>
+            <connection from=":C_0" to="C2E" fromLane="0" toLane="0" dir="s"/>
+        </net>
+    "#;
+
+    fn layout(xml: &str, junction: Option<&str>) -> Result<Layout, String> {
+        junction_layout(&Document::parse(xml).unwrap(), junction)
+    }
+
+    fn points(layout: &Layout, movement: Going) -> Vec<[f32; 2]> {
+        let lane = layout.lanes.iter().find(|lane| lane.movement == movement).unwrap();
+        assert_eq!(lane.from, Direction::West);
+        lane.points.clone().unwrap()
+    }
+
+    fn assert_near(actual: &[[f32; 2]], expected: &[[f32; 2]]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+        for (a, b) in actual.iter().zip(expected) {
+            assert!(Vec2::from(*a).distance(Vec2::from(*b)) < 1e-3, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn imports_one_lane_per_movement_in_world_coordinates() {
+        let layout = layout(T_JUNCTION, None).unwrap();
+        assert_eq!(layout.lanes.len(), 3);
+        assert!(layout.lanes.iter().all(|lane| lane.speed_limit == Some(13.89)));
+
+        let straight = points(&layout, Going::Straight);
+        assert_near(&straight, &[[0.0, 36.6], [25.0, 36.6], [45.0, 36.6], [70.0, 36.6]]);
+
+        let (left, right) = (points(&layout, Going::Left), points(&layout, Going::Right));
+        assert_near(&left[left.len() - 1..], &[[36.6, 0.0]]);
+        assert_near(&right[right.len() - 1..], &[[33.4, 70.0]]);
+    }
+
+    #[test]
+    fn chooses_the_junction() {
+        assert!(layout(T_JUNCTION, Some("C")).is_ok());
+        assert_eq!(layout(T_JUNCTION, Some("X")).unwrap_err(), "no junction `X`");
+
+        let two = T_JUNCTION.replace(r#"type="dead_end""#, r#"type="priority""#);
+        assert_eq!(layout(&two, None).unwrap_err(), "several junctions, choose one of W, C");
+        assert_eq!(layout("<net/>", None).unwrap_err(), "no junction");
+    }
+
+    #[test]
+    fn rejects_invalid_shapes() {
+        let broken = T_JUNCTION.replace("0,98.4 90,98.4", "0,98.4 90");
+        assert_eq!(layout(&broken, None).unwrap_err(), "invalid shape of lane `W2C_0`");
+
+        let short = T_JUNCTION.replace("0,98.4 90,98.4", "90,98.4");
+        assert_eq!(layout(&short, None).unwrap_err(), "lane `W2C_0` has no shape");
+    }
+}
@@ -0,0 +1,358 @@
+use crate::constant::{TICK, V2I_REQUEST_DISTANCE, V2I_REQUEST_PERIOD, V2I_RESERVATION_TIMEOUT, V2I_STATUS_PERIOD};
+use crate::traffic::{CarStatus, Direction, Geometry, Going, Line, Params};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Radio link between the cars and the intersection, read from the `[v2i]` table of a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelConfig {
+    /// Minimum delivery delay, in seconds
+    pub latency: f64,
+
+    /// Random delay added to the latency, uniform between zero and this value
+    pub jitter: f64,
+
+    /// Probability for a message to be lost
+    pub loss: f64,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfig {
+            latency: 0.05,
+            jitter: 0.02,
+            loss: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Message {
+    /// A car asks to cross the intersection on its lane
+    Request { car: usize, lane: (Direction, Going) },
+    Grant { car: usize },
+    Reject { car: usize },
+
+    /// Periodic report of a car, and last report with `AfterTurn` when it leaves the map
+    Status { car: usize, lane: (Direction, Going), status: CarStatus },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MessageCounts {
+    pub sent: usize,
+    pub lost: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Reservation {
+    lane: (Direction, Going),
+
+    /// Delivery time of the last message of the car
+    last_heard: f64,
+}
+
+/// Request the manager could not grant yet, served before the later requests of the crossing lanes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Pending {
+    lane: (Direction, Going),
+
+    /// Delivery time of the first request of the car
+    since: f64,
+
+    /// Delivery time of the last message of the car
+    last_heard: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InFlight {
+    delivery_time: f64,
+    message: Message,
+}
+
+/// Messaging between the cars and the intersection manager, which only knows the cars
+/// through their messages and grants each crossing a reservation of its lane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct V2i {
+    pub config: ChannelConfig,
+    pub counts: MessageCounts,
+    in_flight: Vec<InFlight>,
+
+    /// Lanes reserved by the cars granted by the manager, freed when the car leaves
+    /// or is not heard for `V2I_RESERVATION_TIMEOUT`
+    reservations: HashMap<usize, Reservation>,
+
+    /// Requests waiting for a grant, dropped like the reservations when the car is not heard
+    pending: HashMap<usize, Pending>,
+
+    /// Time of the last request of each waiting car
+    last_requests: HashMap<usize, f64>,
+
+    /// Lanes of the cars on the map at the last call of `send_car_messages`
+    connected: HashMap<usize, (Direction, Going)>,
+}
+
+impl V2i {
+    pub fn new(config: ChannelConfig) -> V2i {
+        V2i {
+            config,
+            counts: MessageCounts::default(),
+            in_flight: Vec::new(),
+            reservations: HashMap::new(),
+            pending: HashMap::new(),
+            last_requests: HashMap::new(),
+            connected: HashMap::new(),
+        }
+    }
+
+    fn send(&mut self, time: f64, message: Message, rng: &mut StdRng) {
+        self.counts.sent += 1;
+        if rng.gen_bool(self.config.loss.clamp(0.0, 1.0)) {
+            self.counts.lost += 1;
+            return;
+        }
+
+        let jitter = rng.gen::<f64>() * self.config.jitter.max(0.0);
+        self.in_flight.push(InFlight {
+            delivery_time: time + self.config.latency.max(0.0) + jitter,
+            message,
+        });
+    }
+
+    /// Hands the messages due at `time` to the manager or to the cars
+    pub fn deliver(&mut self, time: f64, lines: &mut [Line], geometry: &Geometry, rng: &mut StdRng) {
+        let (due, in_flight) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition::<Vec<_>, _>(|message| message.delivery_time <= time);
+        self.in_flight = in_flight;
+
+        for InFlight { message, .. } in due {
+            match message {
+                Message::Request { car, lane } => {
+                    let reply = self.manage_request(time, car, lane, geometry);
+                    self.send(time, reply, rng);
+                }
+                Message::Status { car, lane, status } => match status {
+                    // a car inside without a known reservation, after a restore, keeps its lane
+                    CarStatus::BeforeTurn | CarStatus::Turning => {
+                        if let Some(pending) = self.pending.get_mut(&car) {
+                            pending.last_heard = time;
+                        }
+                        if let Some(reservation) = self.reservations.get_mut(&car) {
+                            reservation.last_heard = time;
+                        } else if status == CarStatus::Turning {
+                            self.reservations.insert(car, Reservation { lane, last_heard: time });
+                        }
+                    }
+                    CarStatus::AfterTurn => {
+                        self.reservations.remove(&car);
+                        self.pending.remove(&car);
+                    }
+                },
+                Message::Grant { car } => {
+                    if let Some(car) = lines.iter_mut().flat_map(|line| line.path_cars.iter_mut().flatten()).find(|other| other.id == car) {
+                        car.granted = true;
+                    }
+                }
+                Message::Reject { .. } => {}
+            }
+        }
+
+        self.reservations
+            .retain(|_, reservation| time - reservation.last_heard <= V2I_RESERVATION_TIMEOUT);
+        self.pending
+            .retain(|_, pending| time - pending.last_heard <= V2I_RESERVATION_TIMEOUT);
+    }
+
+    /// Grants the lane unless a crossing lane is reserved by another car, or asked for
+    /// earlier by a car still waiting, so that the requests of crossing lanes are served in order
+    fn manage_request(&mut self, time: f64, car: usize, lane: (Direction, Going), geometry: &Geometry) -> Message {
+        // asking again after a lost grant
+        if let Some(reservation) = self.reservations.get_mut(&car) {
+            reservation.last_heard = time;
+            return Message::Grant { car };
+        }
+
+        let pending = *self
+            .pending
+            .entry(car)
+            .and_modify(|pending| pending.last_heard = time)
+            .or_insert(Pending { lane, since: time, last_heard: time });
+
+        let crossing = geometry.crossing_lanes(lane);
+        let reserved = self
+            .reservations
+            .values()
+            .any(|reservation| crossing.contains(&reservation.lane));
+        let earlier = self.pending.iter().any(|(&other, other_pending)| {
+            crossing.contains(&other_pending.lane) && (other_pending.since, other) < (pending.since, car)
+        });
+
+        if reserved || earlier {
+            return Message::Reject { car };
+        }
+
+        self.pending.remove(&car);
+        self.reservations.insert(car, Reservation { lane, last_heard: time });
+        Message::Grant { car }
+    }
+
+    /// Status of every car each `V2I_STATUS_PERIOD`, requests of the first waiting car of
+    /// each lane near its stop line, and last status of the cars which left the map
+    pub fn send_car_messages(&mut self, time: f64, lines: &[Line], params: &Params, rng: &mut StdRng) {
+        let status_due = (time / V2I_STATUS_PERIOD).floor() != ((time - TICK) / V2I_STATUS_PERIOD).floor();
+        let request_distance = V2I_REQUEST_DISTANCE.max(params.safe_distance);
+        let cars = lines.iter().flat_map(|line| {
+            line.path_cars.iter().flat_map(move |cars| {
+                cars.iter().enumerate().map(move |(index, car)| {
+                    let first = cars[..index].iter().all(|other| other.get_status() != CarStatus::BeforeTurn)
+                        && line.shared_lane_leader(car).is_none();
+                    (car, first)
+                })
+            })
+        });
+
+        let mut connected = HashMap::new();
+        let mut waiting = Vec::new();
+        for (car, first) in cars {
+            let lane = (car.path.coming_from, car.path.going_to);
+            let status = car.get_status();
+            connected.insert(car.id, lane);
+
+            if status_due {
+                self.send(time, Message::Status { car: car.id, lane, status }, rng);
+            }
+
+            if status == CarStatus::BeforeTurn && !car.granted && first && car.stop_line_distance() <= request_distance {
+                waiting.push(car.id);
+                let last_request = self.last_requests.get(&car.id).copied();
+                if last_request.is_none_or(|last_request| time >= last_request + V2I_REQUEST_PERIOD) {
+                    self.last_requests.insert(car.id, time);
+                    self.send(time, Message::Request { car: car.id, lane }, rng);
+                }
+            }
+        }
+
+        self.last_requests.retain(|car, _| waiting.contains(car));
+
+        let connected = std::mem::replace(&mut self.connected, connected);
+        for (car, lane) in connected {
+            if !self.connected.contains_key(&car) {
+                let status = CarStatus::AfterTurn;
+                self.send(time, Message::Status { car, lane, status }, rng);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::TrafficState;
+    use rand::SeedableRng;
+
+    const NORTH: (Direction, Going) = (Direction::North, Going::Straight);
+    const EAST: (Direction, Going) = (Direction::East, Going::Straight);
+    const SOUTH: (Direction, Going) = (Direction::South, Going::Straight);
+
+    fn lossless() -> V2i {
+        V2i::new(ChannelConfig { latency: 0.0, jitter: 0.0, loss: 0.0 })
+    }
+
+    #[test]
+    fn grants_lanes_that_do_not_cross_a_reservation() {
+        let (mut v2i, geometry) = (lossless(), Geometry::default());
+
+        assert_eq!(v2i.manage_request(0.0, 1, NORTH, &geometry), Message::Grant { car: 1 });
+        assert_eq!(v2i.manage_request(0.0, 2, SOUTH, &geometry), Message::Grant { car: 2 });
+        assert_eq!(v2i.manage_request(0.0, 3, EAST, &geometry), Message::Reject { car: 3 });
+        assert_eq!(v2i.manage_request(0.1, 1, NORTH, &geometry), Message::Grant { car: 1 });
+    }
+
+    #[test]
+    fn serves_the_requests_of_crossing_lanes_in_order() {
+        let (mut v2i, geometry) = (lossless(), Geometry::default());
+        let mut traffic_state = TrafficState::with_geometry(1, Default::default());
+
+        assert_eq!(v2i.manage_request(0.0, 1, NORTH, &geometry), Message::Grant { car: 1 });
+        assert_eq!(v2i.manage_request(0.1, 2, EAST, &geometry), Message::Reject { car: 2 });
+
+        // the south lane does not cross the north one, but the east car asked first
+        assert_eq!(v2i.manage_request(0.2, 3, SOUTH, &geometry), Message::Reject { car: 3 });
+
+        v2i.in_flight.push(InFlight {
+            delivery_time: 0.3,
+            message: Message::Status { car: 1, lane: NORTH, status: CarStatus::AfterTurn },
+        });
+        v2i.deliver(0.3, &mut traffic_state.lines, &geometry, &mut traffic_state.rng);
+        assert_eq!(v2i.manage_request(0.4, 3, SOUTH, &geometry), Message::Reject { car: 3 });
+        assert_eq!(v2i.manage_request(0.5, 2, EAST, &geometry), Message::Grant { car: 2 });
+    }
+
+    #[test]
+    fn frees_the_lanes_of_cars_not_heard() {
+        let (mut v2i, geometry) = (lossless(), Geometry::default());
+        let mut traffic_state = TrafficState::with_geometry(1, Default::default());
+
+        assert_eq!(v2i.manage_request(0.0, 1, NORTH, &geometry), Message::Grant { car: 1 });
+        v2i.deliver(V2I_RESERVATION_TIMEOUT / 2.0, &mut traffic_state.lines, &geometry, &mut traffic_state.rng);
+        assert_eq!(v2i.manage_request(0.6, 2, EAST, &geometry), Message::Reject { car: 2 });
+
+        // the pending east request is refreshed by its status, the north reservation is not
+        let time = V2I_RESERVATION_TIMEOUT + 0.1;
+        v2i.in_flight.push(InFlight {
+            delivery_time: time,
+            message: Message::Status { car: 2, lane: EAST, status: CarStatus::BeforeTurn },
+        });
+        v2i.deliver(time, &mut traffic_state.lines, &geometry, &mut traffic_state.rng);
+        assert!(v2i.reservations.is_empty());
+        assert!(v2i.pending.contains_key(&2));
+        assert_eq!(v2i.manage_request(time, 2, EAST, &geometry), Message::Grant { car: 2 });
+    }
+
+    #[test]
+    fn loses_and_delays_messages() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let message = Message::Grant { car: 1 };
+
+        let mut v2i = V2i::new(ChannelConfig { latency: 0.1, jitter: 0.0, loss: 1.0 });
+        v2i.send(0.0, message, &mut rng);
+        assert_eq!((v2i.counts.sent, v2i.counts.lost), (1, 1));
+        assert!(v2i.in_flight.is_empty());
+
+        let mut v2i = V2i::new(ChannelConfig { latency: 0.1, jitter: 0.05, loss: 0.0 });
+        v2i.send(1.0, message, &mut rng);
+        assert_eq!((v2i.counts.sent, v2i.counts.lost), (1, 0));
+        let delivery_time = v2i.in_flight[0].delivery_time;
+        assert!((1.1..=1.15).contains(&delivery_time), "{}", delivery_time);
+    }
+
+    #[test]
+    fn only_the_first_car_of_a_lane_asks_near_its_stop_line() {
+        let mut traffic_state = TrafficState::with_geometry(4, Default::default());
+        traffic_state.v2i = Some(lossless());
+
+        for tick in 0..600 {
+            if tick % 10 == 0 {
+                traffic_state.gen_car(Direction::North);
+            }
+            traffic_state.update();
+
+            let v2i = traffic_state.v2i.as_ref().unwrap();
+            let requests = v2i.in_flight.iter().filter_map(|in_flight| match in_flight.message {
+                Message::Request { car, .. } => Some(car),
+                _ => None,
+            });
+            for car in requests {
+                let car = traffic_state.cars().find(|other| other.id == car).unwrap();
+                let line = &traffic_state.lines[car.path.coming_from as usize];
+                let ahead = line.path_cars(&car.path).iter().take_while(|other| other.id != car.id);
+                assert!(ahead.filter(|other| other.get_status() == CarStatus::BeforeTurn).count() == 0);
+                assert!(car.stop_line_distance() <= V2I_REQUEST_DISTANCE);
+            }
+        }
+        assert!(traffic_state.v2i.unwrap().counts.sent > 0);
+    }
+}